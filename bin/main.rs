@@ -12,6 +12,7 @@ struct Opt {
 }
 
 fn valid_filepath(file_path: &str) -> Result<(), String> {
+    #[allow(clippy::redundant_pattern_matching)]
     if let Err(_) = fs::metadata(file_path) {
         return Err("file not exist".into());
     }
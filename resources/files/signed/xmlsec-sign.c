@@ -0,0 +1,55 @@
+/* Sign an OPC signature template with libxmlsec1.
+ * usage: sign <template.xml> <key.pem> <cert.pem> <package dir> > sig.xml
+ * Manifest URIs like /xl/workbook.xml?ContentType=... are read from <package dir>. */
+#include <stdio.h>
+#include <string.h>
+#include <libxml/tree.h>
+#include <libxml/parser.h>
+#include <xmlsec/xmlsec.h>
+#include <xmlsec/xmltree.h>
+#include <xmlsec/xmldsig.h>
+#include <xmlsec/io.h>
+#include <xmlsec/crypto.h>
+
+static const char *root;
+
+static int match(const char *uri) { return uri != NULL && uri[0] == '/'; }
+static void *open_part(const char *uri) {
+    char path[4096];
+    snprintf(path, sizeof path, "%s%s", root, uri);
+    char *q = strchr(path + strlen(root), '?');
+    if (q) *q = 0;
+    fprintf(stderr, "reading %s\n", path);
+    return fopen(path, "rb");
+}
+static int read_part(void *ctx, char *buf, int len) { return (int)fread(buf, 1, len, ctx); }
+static int close_part(void *ctx) { return fclose(ctx); }
+
+int main(int argc, char **argv) {
+    if (argc != 5) return 2;
+    root = argv[4];
+    xmlInitParser();
+    LIBXML_TEST_VERSION
+    if (xmlSecInit() < 0 || xmlSecCryptoAppInit(NULL) < 0 || xmlSecCryptoInit() < 0) return 1;
+    xmlSecIOCleanupCallbacks();
+    xmlSecIORegisterCallbacks((xmlInputMatchCallback)match, (xmlInputOpenCallback)open_part,
+                              (xmlInputReadCallback)read_part, (xmlInputCloseCallback)close_part);
+
+    xmlDocPtr doc = xmlReadFile(argv[1], NULL, 0);
+    if (!doc) return 1;
+    xmlNodePtr node = xmlSecFindNode(xmlDocGetRootElement(doc), xmlSecNodeSignature, xmlSecDSigNs);
+    if (!node) return 1;
+    /* Register the Id attributes the SignedInfo references point at. */
+    const xmlChar *ids[] = { BAD_CAST "Id", NULL };
+    xmlSecAddIDs(doc, xmlDocGetRootElement(doc), ids);
+
+    xmlSecDSigCtxPtr ctx = xmlSecDSigCtxCreate(NULL);
+    ctx->signKey = xmlSecCryptoAppKeyLoad(argv[2], xmlSecKeyDataFormatPem, NULL, NULL, NULL);
+    if (!ctx->signKey) return 1;
+    if (xmlSecCryptoAppKeyCertLoad(ctx->signKey, argv[3], xmlSecKeyDataFormatPem) < 0) return 1;
+    if (xmlSecDSigCtxSign(ctx, node) < 0) { fprintf(stderr, "sign failed\n"); return 1; }
+    xmlDocDump(stdout, doc);
+    xmlSecDSigCtxDestroy(ctx);
+    xmlFreeDoc(doc);
+    return 0;
+}
@@ -1,10 +1,17 @@
 use crate::document::sheet::cell::CellValue;
 use crate::document::sheet::style::CellFormatComponent;
 use crate::document::sheet::worksheet::SheetCol;
-use crate::error::Result;
+use crate::error::{OoxmlError, Result};
 use crate::packaging::element::OpenXmlDeserialized;
-use crate::packaging::{package::OpenXmlPackage, relationship::Relationships};
+use crate::packaging::relationship::{
+    EXTENDED_PROPERTIES_RELATIONSHIP_TYPE, OFFICE_DOCUMENT_RELATIONSHIP_TYPE,
+};
+use crate::packaging::{
+    namespace::Conformance, options::OpenOptions, package::OpenXmlPackage,
+    relationship::Relationships, uri::PackUri,
+};
 use std::cell::RefCell;
+use std::io::{Read, Seek};
 use std::path::Path;
 use std::rc::Rc;
 
@@ -15,9 +22,28 @@ mod style;
 mod workbook;
 mod worksheet;
 
+pub const WORKSHEET_RELATIONSHIP_TYPE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet";
+pub const SHARED_STRINGS_RELATIONSHIP_TYPE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/sharedStrings";
+pub const STYLES_RELATIONSHIP_TYPE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles";
+
+/// Namespaces the typed spreadsheet parts model besides SpreadsheetML itself.
+pub(crate) const SPREADSHEETML_NAMESPACES: &[&str] =
+    &["http://schemas.openxmlformats.org/officeDocument/2006/relationships"];
+
+pub const CHARTSHEET_RELATIONSHIP_TYPE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/chartsheet";
+
+/// Headings of the worksheet and chartsheet titles in the extended properties.
+const WORKSHEETS_HEADING: &str = "Worksheets";
+const CHARTS_HEADING: &str = "Charts";
+
+pub use self::document_type::SpreadsheetDocumentType;
 use self::{
-    document_type::SpreadsheetDocumentType, shared_string::SharedStringsPart, style::StylesPart,
-    workbook::WorkbookPart, worksheet::WorksheetPart,
+    shared_string::SharedStringsPart, style::StylesPart, workbook::WorkbookPart,
+    worksheet::WorksheetPart,
 };
 
 #[derive(Default, Debug)]
@@ -31,11 +57,41 @@ pub struct SpreadsheetDocument {
 impl SpreadsheetDocument {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
         let package = OpenXmlPackage::open(path)?;
+        Self::from_package(package)
+    }
+
+    pub fn open_with_options<P: AsRef<Path>>(path: P, options: OpenOptions) -> Result<Self> {
+        let package = OpenXmlPackage::open_with_options(path, options)?;
+        Self::from_package(package)
+    }
+
+    /// Open a password protected spreadsheet.
+    pub fn open_with_password<P: AsRef<Path>>(path: P, password: &str) -> Result<Self> {
+        let package = OpenXmlPackage::open_with_password(path, password)?;
+        Self::from_package(package)
+    }
+
+    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Self> {
+        let package = OpenXmlPackage::from_reader(reader)?;
+        Self::from_package(package)
+    }
+
+    pub fn from_bytes<B: AsRef<[u8]>>(bytes: B) -> Result<Self> {
+        let package = OpenXmlPackage::from_bytes(bytes)?;
+        Self::from_package(package)
+    }
+
+    fn from_package(package: OpenXmlPackage) -> Result<Self> {
         let package = Rc::new(RefCell::new(package));
-        let parts = SpreadsheetParts::from_package(package.clone());
+        let parts = SpreadsheetParts::from_package(package.clone())?;
+        let document_type = package
+            .borrow()
+            .get_part(parts.workbook_uri.as_str())
+            .and_then(|part| part.content_type())
+            .and_then(|content_type| SpreadsheetDocumentType::from_content_type(content_type))
+            .unwrap_or_default();
         let parts = Rc::new(RefCell::new(parts));
-        let workbook = Workbook::new(parts.clone());
-        let document_type = SpreadsheetDocumentType::Workbook;
+        let workbook = Workbook::new(parts.clone())?;
         Ok(Self {
             package,
             parts,
@@ -44,15 +100,96 @@ impl SpreadsheetDocument {
         })
     }
 
+    /// Save the spreadsheet, first updating the sheet titles of the extended properties
+    /// when they do not match the sheets of the workbook, see
+    /// [`SpreadsheetDocument::update_app_properties`].
+    ///
+    /// Content the package does not model is kept in the package-level XML it regenerates:
+    /// `[Content_Types].xml`, the relationship parts and the document properties. The
+    /// spreadsheet parts cannot be edited and are written from the data that was read.
+    pub fn save<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.update_app_properties()?;
+        self.package.borrow().save(path)
+    }
+
+    /// Regenerate the worksheet and chartsheet titles of the extended properties from the
+    /// workbook sheets.
+    ///
+    /// A heading holds the worksheets or chartsheets when it is named `Worksheets` or
+    /// `Charts`, or lists one of their names, so localized headings keep their name.
+    /// Other headings, e.g. named ranges, are kept. Nothing is written when the titles
+    /// are up to date or the package has no extended properties part.
+    ///
+    /// Sheets can not be added or removed through this crate yet, so the titles only
+    /// change when another tool left them out of date.
+    pub fn update_app_properties(&mut self) -> Result<()> {
+        if self
+            .package
+            .borrow()
+            .get_related_part(EXTENDED_PROPERTIES_RELATIONSHIP_TYPE)
+            .is_none()
+        {
+            return Ok(());
+        }
+        let (worksheets, chartsheets) = self.parts.borrow().sheet_titles();
+        let mut properties = self.package.borrow().app_properties().clone();
+        let current = properties.titles_of_parts();
+
+        let mut parts = current.clone();
+        let mut claimed = Vec::new();
+        for (heading, titles) in [
+            (WORKSHEETS_HEADING, worksheets),
+            (CHARTS_HEADING, chartsheets),
+        ] {
+            let index = parts.iter().enumerate().position(|(index, (name, old))| {
+                !claimed.contains(&index)
+                    && (name.eq_ignore_ascii_case(heading)
+                        || old.iter().any(|title| titles.contains(title)))
+            });
+            match index {
+                Some(index) => {
+                    parts[index].1 = titles;
+                    claimed.push(index);
+                }
+                None if !titles.is_empty() => {
+                    let index = claimed.iter().max().map_or(0, |index| index + 1);
+                    parts.insert(index, (heading.to_string(), titles));
+                    claimed.push(index);
+                }
+                None => {}
+            }
+        }
+        parts.retain(|(_, titles)| !titles.is_empty());
+        if parts == current {
+            return Ok(());
+        }
+        properties.set_titles_of_parts(parts);
+        self.package.borrow_mut().set_app_properties(properties)
+    }
+
     pub fn get_workbook(&self) -> &Workbook {
         &self.workbook
     }
+
+    pub fn document_type(&self) -> SpreadsheetDocumentType {
+        self.document_type
+    }
+
+    /// Conformance class of the document, Strict when the workbook is written in Strict
+    /// namespaces.
+    pub fn conformance(&self) -> Conformance {
+        self.parts.borrow().workbook.conformance()
+    }
+
+    pub fn package(&self) -> std::cell::Ref<'_, OpenXmlPackage> {
+        self.package.borrow()
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct SpreadsheetParts {
-    initialized: bool,
     pub package: Rc<RefCell<OpenXmlPackage>>,
+    pub workbook_uri: PackUri,
     pub relationships: Relationships,
     pub workbook: WorkbookPart,
     pub styles: StylesPart,
@@ -61,22 +198,29 @@ pub struct SpreadsheetParts {
 }
 
 impl SpreadsheetParts {
-    pub fn parse_worksheets(&mut self) {
+    pub fn parse_worksheets(&mut self) -> Result<()> {
         for sheet in &self.workbook.sheets.sheets {
-            let relationship = self
-                .relationships
-                .get_relationship_by_id(&sheet.r_id)
-                .expect("the worksheet relationship does not exist");
-            let worksheet_uri = relationship.target();
+            let worksheet_uri = self.worksheet_uri(&sheet.r_id)?;
             let package = self.package.borrow();
             let part = package
-                .get_part(&format!("xl/{}", worksheet_uri))
-                .expect("get worksheet part by uri");
-            let sheet = WorksheetPart::from_xml_reader(part.as_part_bytes())
-                .expect("parse worksheet error");
+                .get_part(worksheet_uri.as_str())
+                .ok_or_else(|| OoxmlError::PartNotFound(worksheet_uri.to_string()))?;
+            let sheet = WorksheetPart::from_part(part)?;
 
-            self.worksheets.insert(worksheet_uri.into(), sheet);
+            self.worksheets.insert(worksheet_uri.to_string(), sheet);
         }
+        Ok(())
+    }
+
+    /// Resolve the part name of the worksheet with relationship id `r_id`.
+    pub fn worksheet_uri(&self, r_id: &str) -> Result<PackUri> {
+        self.relationships
+            .get_relationship_by_id(r_id)
+            .and_then(|relationship| relationship.target_part(&self.workbook_uri))
+            .ok_or_else(|| OoxmlError::RelationshipNotFound {
+                part: self.workbook_uri.to_string(),
+                relationship: r_id.to_string(),
+            })
     }
 
     pub fn get_worksheet_part<T: AsRef<str>>(&self, uri: T) -> Option<&WorksheetPart> {
@@ -87,6 +231,24 @@ impl SpreadsheetParts {
         self.workbook.sheet_names()
     }
 
+    /// The names of the worksheets and of the chartsheets, in workbook order.
+    pub fn sheet_titles(&self) -> (Vec<String>, Vec<String>) {
+        let mut worksheets = Vec::new();
+        let mut chartsheets = Vec::new();
+        for sheet in &self.workbook.sheets.sheets {
+            let is_chartsheet = self
+                .relationships
+                .get_relationship_by_id(&sheet.r_id)
+                .is_some_and(|relationship| relationship.has_type(CHARTSHEET_RELATIONSHIP_TYPE));
+            if is_chartsheet {
+                chartsheets.push(sheet.name.clone());
+            } else {
+                worksheets.push(sheet.name.clone());
+            }
+        }
+        (worksheets, chartsheets)
+    }
+
     pub fn get_shared_string(&self, idx: usize) -> Option<&str> {
         self.shared_strings.get_shared_string(idx)
     }
@@ -97,43 +259,62 @@ impl SpreadsheetParts {
 }
 
 impl SpreadsheetParts {
-    pub fn from_package(package: Rc<RefCell<OpenXmlPackage>>) -> Self {
-        let relationships = {
+    pub fn from_package(package: Rc<RefCell<OpenXmlPackage>>) -> Result<Self> {
+        let workbook_uri = {
             let package = package.borrow();
-            let part = package.get_part("xl/_rels/workbook.xml.rels").unwrap();
-            Relationships::parse_from_xml_reader(part.as_part_bytes())
+            let workbook_uri = package
+                .relationships()
+                .get_relationships_by_type(OFFICE_DOCUMENT_RELATIONSHIP_TYPE)
+                .find_map(|relationship| relationship.target_part(&PackUri::root()));
+            workbook_uri.ok_or_else(|| OoxmlError::RelationshipNotFound {
+                part: PackUri::root().to_string(),
+                relationship: OFFICE_DOCUMENT_RELATIONSHIP_TYPE.to_string(),
+            })?
         };
 
+        let relationships = package
+            .borrow()
+            .relationships_of(workbook_uri.as_str())
+            .cloned()
+            .unwrap_or_default();
+
         let workbook = {
             let package = package.borrow();
-            let part = package.get_part("xl/workbook.xml").unwrap();
-            WorkbookPart::from_xml_reader(part.as_part_bytes()).expect("workbook main part error")
+            let part = package
+                .get_part(workbook_uri.as_str())
+                .ok_or_else(|| OoxmlError::PartNotFound(workbook_uri.to_string()))?;
+            WorkbookPart::from_part(part)?
         };
 
-        let shared_strings = {
-            let package = package.borrow();
-            let part = package.get_part("xl/sharedStrings.xml").unwrap();
-            SharedStringsPart::from_xml_reader(part.as_part_bytes())
-                .expect("workbook shared strings error")
+        let shared_strings = match package
+            .borrow()
+            .related_parts(workbook_uri.as_str(), SHARED_STRINGS_RELATIONSHIP_TYPE)
+            .first()
+        {
+            Some(part) => SharedStringsPart::from_part(part)?,
+            None => SharedStringsPart::default(),
         };
 
-        let styles = {
-            let package = package.borrow();
-            let part = package.get_part("xl/styles.xml").unwrap();
-            StylesPart::from_xml_reader(part.as_part_bytes()).expect("workbook styles error")
+        let styles = match package
+            .borrow()
+            .related_parts(workbook_uri.as_str(), STYLES_RELATIONSHIP_TYPE)
+            .first()
+        {
+            Some(part) => StylesPart::from_part(part)?,
+            None => StylesPart::default(),
         };
 
         let mut this = Self {
             package,
+            workbook_uri,
             relationships,
             workbook,
             shared_strings,
             styles,
-            initialized: true,
             ..Default::default()
         };
-        this.parse_worksheets();
-        this
+        this.parse_worksheets()?;
+        Ok(this)
     }
 }
 
@@ -144,19 +325,17 @@ pub struct Workbook {
 }
 
 impl Workbook {
-    pub fn new(parts: impl Into<Rc<RefCell<SpreadsheetParts>>>) -> Self {
+    pub fn new(parts: impl Into<Rc<RefCell<SpreadsheetParts>>>) -> Result<Self> {
         let parts = parts.into();
         let borrowed_parts = parts.borrow();
         let mut worksheets = Vec::new();
 
         for sheet in &borrowed_parts.workbook.sheets.sheets {
-            let relationship = borrowed_parts
-                .relationships
-                .get_relationship_by_id(&sheet.r_id)
-                .expect("the worksheet relationship does not exist");
-            let worksheet_uri = relationship.target();
+            let worksheet_uri = borrowed_parts.worksheet_uri(&sheet.r_id)?;
 
-            let part = borrowed_parts.get_worksheet_part(&worksheet_uri).unwrap();
+            let part = borrowed_parts
+                .get_worksheet_part(&worksheet_uri)
+                .ok_or_else(|| OoxmlError::PartNotFound(worksheet_uri.to_string()))?;
 
             let worksheet = Worksheet {
                 parts: parts.clone(),
@@ -168,10 +347,10 @@ impl Workbook {
             worksheets.push(worksheet);
         }
 
-        Self {
+        Ok(Self {
             parts: parts.clone(),
             worksheets,
-        }
+        })
     }
 
     pub fn worksheet_names(&self) -> Vec<String> {
@@ -188,6 +367,7 @@ impl Workbook {
     }
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct Worksheet {
     parts: Rc<RefCell<SpreadsheetParts>>,
@@ -240,13 +420,13 @@ impl Worksheet {
             if let Ok(days) = raw.parse::<i64>() {
                 let days = days - 25569;
                 let secs = days * 86400;
-                chrono::NaiveDateTime::from_timestamp_opt(secs, 0)
+                chrono::DateTime::from_timestamp(secs, 0).map(|dt| dt.naive_utc())
             } else if let Ok(datetime) = raw.parse::<f64>() {
                 let unix_days = datetime - 25569.;
                 let unix_secs = unix_days * 86400.;
                 let secs = unix_secs.trunc() as i64;
                 let nsecs = (unix_secs.fract().abs() * 1e9) as u32;
-                chrono::NaiveDateTime::from_timestamp_opt(secs, nsecs)
+                chrono::DateTime::from_timestamp(secs, nsecs).map(|dt| dt.naive_utc())
             } else {
                 None
             }
@@ -271,7 +451,7 @@ impl Worksheet {
             (regex::Regex::new("\\\\").unwrap(), ""),
         ];
         let s = match code {
-            s if s == "General" => CellValue::String(raw.to_string()),
+            "General" => CellValue::String(raw.to_string()),
             format if datetime_re.is_match(format) | format.ends_with(";@") => {
                 // dbg!(&format);
                 let format = format.trim_end_matches(";@");
@@ -350,11 +530,7 @@ impl<'a> Cell<'a> {
     }
 
     pub fn value(&self) -> Option<CellValue> {
-        let inner = self.inner();
-        if inner.is_none() {
-            return None;
-        }
-        let inner = inner.unwrap();
+        let inner = self.inner()?;
         let raw = inner.as_raw_str();
         let ctype = inner.cell_type();
         let value = match ctype {
@@ -364,15 +540,15 @@ impl<'a> Cell<'a> {
             cell::CellType::Shared(shared_string_id) => CellValue::String(
                 self.sheet
                     .get_shared_string(shared_string_id)
-                    .expect(&format!("shared string not found {}", shared_string_id)),
+                    .unwrap_or_else(|| panic!("shared string not found {}", shared_string_id)),
             ),
             cell::CellType::Styled(style_id) => self
                 .sheet
-                .to_cell_value(&inner.as_raw_str(), style_id)
+                .to_cell_value(inner.as_raw_str(), style_id)
                 .expect("format with cell style"),
             cell::CellType::StyledNumber(style_id) => self
                 .sheet
-                .to_cell_value(&inner.as_raw_str(), style_id)
+                .to_cell_value(inner.as_raw_str(), style_id)
                 .expect("format with cell style"),
         };
         Some(value)
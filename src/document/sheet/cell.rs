@@ -33,7 +33,7 @@ impl CellValue {
             CellValue::Raw(v) => v.clone(),
             CellValue::Bool(_b) => panic!("unsupported cell type: bool"),
             CellValue::Double(f) => format!("{}", f),
-            CellValue::DateTime(datetime, format) => format!("{}", datetime.format(&format)),
+            CellValue::DateTime(datetime, format) => format!("{}", datetime.format(format)),
             _ => unimplemented!(),
         }
     }
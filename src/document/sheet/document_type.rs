@@ -1,4 +1,16 @@
-#[derive(Debug, Clone, Copy)]
+use crate::packaging::content_type::content_type_eq;
+
+pub const WORKBOOK_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml";
+pub const TEMPLATE_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.template.main+xml";
+pub const MACRO_ENABLED_WORKBOOK_CONTENT_TYPE: &str =
+    "application/vnd.ms-excel.sheet.macroEnabled.main+xml";
+pub const MACRO_ENABLED_TEMPLATE_CONTENT_TYPE: &str =
+    "application/vnd.ms-excel.template.macroEnabled.main+xml";
+pub const ADD_IN_CONTENT_TYPE: &str = "application/vnd.ms-excel.addin.macroEnabled.main+xml";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SpreadsheetDocumentType {
     // excel workbook (*.xlsx).
     Workbook,
@@ -17,3 +29,25 @@ impl Default for SpreadsheetDocumentType {
         SpreadsheetDocumentType::Workbook
     }
 }
+
+impl SpreadsheetDocumentType {
+    /// Get the document type from the content type of the workbook main part.
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        [
+            (WORKBOOK_CONTENT_TYPE, Self::Workbook),
+            (TEMPLATE_CONTENT_TYPE, Self::Template),
+            (
+                MACRO_ENABLED_WORKBOOK_CONTENT_TYPE,
+                Self::MacroEnabledWorkbook,
+            ),
+            (
+                MACRO_ENABLED_TEMPLATE_CONTENT_TYPE,
+                Self::MacroEnabledTemplate,
+            ),
+            (ADD_IN_CONTENT_TYPE, Self::AddIn),
+        ]
+        .iter()
+        .find(|(known, _)| content_type_eq(known, content_type))
+        .map(|(_, document_type)| *document_type)
+    }
+}
@@ -19,7 +19,9 @@ pub struct SharedString {
     t: Option<String>,
 }
 
-impl OpenXmlDeserializeDefault for SharedStringsPart {}
+impl OpenXmlDeserializeDefault for SharedStringsPart {
+    const UNDERSTOOD_NAMESPACES: &'static [&'static str] = super::SPREADSHEETML_NAMESPACES;
+}
 
 impl SharedStringsPart {
     pub fn get_shared_string(&self, idx: usize) -> Option<&str> {
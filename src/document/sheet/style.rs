@@ -18,7 +18,9 @@ pub struct StylesPart {
     namespaces: Namespaces,
 }
 
-impl OpenXmlDeserializeDefault for StylesPart {}
+impl OpenXmlDeserializeDefault for StylesPart {
+    const UNDERSTOOD_NAMESPACES: &'static [&'static str] = super::SPREADSHEETML_NAMESPACES;
+}
 
 impl StylesPart {
     pub fn get_cell_style(&self, id: usize) -> Option<&CellStyle> {
@@ -169,16 +171,19 @@ pub(crate) mod font {
     pub struct FontSize {
         val: f64,
     }
+    #[allow(dead_code)]
     #[derive(Debug, Clone, Default, Serialize, Deserialize)]
     #[serde(rename = "name")]
     pub struct FontName {
         val: String,
     }
+    #[allow(dead_code)]
     #[derive(Debug, Clone, Default, Serialize, Deserialize)]
     #[serde(rename = "charset")]
     pub struct FontCharset {
         val: String,
     }
+    #[allow(dead_code)]
     #[derive(Debug, Clone, Default, Serialize, Deserialize)]
     #[serde(rename = "scheme")]
     pub struct FontScheme {
@@ -220,6 +225,7 @@ pub struct CellFormatComponent<'a> {
     xf: &'a Xf,
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct CellStyleComponent<'a> {
     styles: &'a StylesPart,
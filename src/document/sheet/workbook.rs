@@ -1,4 +1,7 @@
-use crate::packaging::{element::OpenXmlDeserializeDefault, namespace::Namespaces};
+use crate::packaging::{
+    element::OpenXmlDeserializeDefault,
+    namespace::{Conformance, Namespaces},
+};
 
 use serde::{Deserialize, Serialize};
 
@@ -14,9 +17,15 @@ pub struct WorkbookPart {
     namespaces: Namespaces,
 }
 
-impl OpenXmlDeserializeDefault for WorkbookPart {}
+impl OpenXmlDeserializeDefault for WorkbookPart {
+    const UNDERSTOOD_NAMESPACES: &'static [&'static str] = super::SPREADSHEETML_NAMESPACES;
+}
 
 impl WorkbookPart {
+    pub fn conformance(&self) -> Conformance {
+        self.namespaces.conformance()
+    }
+
     pub fn sheet_names(&self) -> Vec<&str> {
         self.sheets
             .sheets
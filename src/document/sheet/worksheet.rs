@@ -1,7 +1,7 @@
 use crate::packaging::namespace::Namespaces;
 
 use crate::document::sheet::cell::CellType;
-use crate::packaging::element::{OpenXmlDeserializeDefault, OpenXmlDeserialized};
+use crate::packaging::element::OpenXmlDeserializeDefault;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
@@ -19,7 +19,9 @@ pub struct WorksheetPart {
     pub header_footer: Option<HeaderFooter>,
 }
 
-impl OpenXmlDeserializeDefault for WorksheetPart {}
+impl OpenXmlDeserializeDefault for WorksheetPart {
+    const UNDERSTOOD_NAMESPACES: &'static [&'static str] = super::SPREADSHEETML_NAMESPACES;
+}
 
 impl WorksheetPart {
     pub fn dimenstion(&self) -> Option<(usize, usize)> {
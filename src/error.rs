@@ -12,10 +12,72 @@ pub enum OoxmlError {
     XmlError(#[from] quick_xml::Error),
     #[error("xml deserialization error")]
     XmlDeError(#[from] quick_xml::de::DeError),
+    #[error("failed to parse part {part} at `{path}`: {source}")]
+    PartParseError {
+        part: String,
+        path: String,
+        source: quick_xml::de::DeError,
+    },
+    #[error("part {0} does not exist in package")]
+    PartNotFound(String),
+    #[error("relationship {relationship} of part {part} does not exist")]
+    RelationshipNotFound { part: String, relationship: String },
+    #[error("invalid digital signature in {part}: {reason}")]
+    InvalidSignature { part: String, reason: String },
+    #[error("package is encrypted, a password is required")]
+    PasswordRequired,
+    #[error("the password is incorrect")]
+    InvalidPassword,
+    #[error("encrypted package error: {0}")]
+    EncryptionError(String),
+    #[error("part {part} is larger than the limit of {limit} bytes")]
+    PartTooLarge { part: String, limit: u64 },
+    #[error("package is larger than the limit of {limit} bytes")]
+    PackageTooLarge { limit: u64 },
+    #[error("package has {count} entries, more than the limit of {limit}")]
+    TooManyParts { count: usize, limit: usize },
+    #[error("part {part} exceeds the compression ratio limit of {limit}")]
+    CompressionRatioExceeded { part: String, limit: u64 },
+    #[error("part {part} nests XML elements deeper than the limit of {limit}")]
+    XmlTooDeep { part: String, limit: usize },
+    #[error("part {part} has invalid pieces: {reason}")]
+    InvalidPieces { part: String, reason: String },
+    #[error("part {part} requires namespace {namespace} which is not understood")]
+    UnsupportedNamespace { part: String, namespace: String },
+    #[error("no property id is left for custom property {0}")]
+    PropertyIdsExhausted(String),
     #[error("No content type in package")]
     PackageContentTypeError,
     #[error("unknown data store error")]
     Unknown,
 }
 
+impl OoxmlError {
+    /// Set the part name of a parse or signature error that was raised without one.
+    pub(crate) fn in_part<S: Into<String>>(self, part_name: S) -> Self {
+        match self {
+            OoxmlError::PartParseError { part, path, source } if part.is_empty() => {
+                OoxmlError::PartParseError {
+                    part: part_name.into(),
+                    path,
+                    source,
+                }
+            }
+            OoxmlError::InvalidSignature { part, reason } if part.is_empty() => {
+                OoxmlError::InvalidSignature {
+                    part: part_name.into(),
+                    reason,
+                }
+            }
+            OoxmlError::UnsupportedNamespace { part, namespace } if part.is_empty() => {
+                OoxmlError::UnsupportedNamespace {
+                    part: part_name.into(),
+                    namespace,
+                }
+            }
+            e => e,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, OoxmlError>;
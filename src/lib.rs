@@ -1,3 +1,12 @@
+#![allow(
+    clippy::derivable_impls,
+    clippy::inherent_to_string_shadow_display,
+    clippy::needless_return,
+    clippy::redundant_static_lifetimes
+)]
+
 pub mod document;
 mod error;
 pub mod packaging;
+
+pub use error::{OoxmlError, Result};
@@ -2,9 +2,17 @@ pub mod app_property;
 pub mod content_type;
 pub mod custom_property;
 pub mod element;
+pub(crate) mod encryption;
+pub mod flat_opc;
+pub(crate) mod mce;
 pub mod namespace;
+pub mod options;
 pub mod package;
 pub mod part;
 pub mod property;
 pub mod relationship;
+pub mod signature;
+pub mod uri;
+pub mod validation;
 pub mod variant;
+pub(crate) mod xml;
@@ -1,58 +1,254 @@
 use super::namespace::Namespaces;
 use super::variant::Variant;
 
-use crate::packaging::element::OpenXmlDeserializeDefault;
+use crate::error::OoxmlError;
+use crate::packaging::element::{OpenXmlDeserializeDefault, OpenXmlDeserialized};
 use serde::{Deserialize, Serialize};
 
 pub const APP_PROPERTIES_URI: &str = "docProps/app.xml";
+pub const APP_PROPERTIES_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.extended-properties+xml";
 
 pub const APP_PROPERTIES_TAG: &str = "Properties";
 pub const APP_PROPERTIES_NAMESPACE_ATTRIBUTE: &str = "xmlns";
 pub const APP_PROPERTIES_NAMESPACE: &str =
-    "http://schemas.openxmlformats.org/officeDocument/2006/app-properties";
+    "http://schemas.openxmlformats.org/officeDocument/2006/extended-properties";
 
 pub const APP_PROPERTY_TAG: &str = "property";
 
+/// Elements modelled by [`AppProperties`], others are kept when the part is rewritten.
+pub(crate) const APP_PROPERTY_TAGS: &[&str] = &[
+    "Template",
+    "Manager",
+    "Company",
+    "Pages",
+    "Words",
+    "Characters",
+    "PresentationFormat",
+    "Lines",
+    "Paragraphs",
+    "Slides",
+    "Notes",
+    "TotalTime",
+    "HiddenSlides",
+    "MMClips",
+    "ScaleCrop",
+    "HeadingPairs",
+    "TitlesOfParts",
+    "LinksUpToDate",
+    "CharactersWithSpaces",
+    "SharedDoc",
+    "HyperlinkBase",
+    "HyperlinksChanged",
+    "Application",
+    "AppVersion",
+    "DocSecurity",
+];
 pub const VT_NAMESPACE_ATTRIBUTE: &str = "xmlns:vt";
 pub const VT_NAMESPACE: &str =
     "http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes";
 
+/// Extended (application defined) properties of a package (ECMA-376 Part 1, 22.2).
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename = "Properties", rename_all = "PascalCase")]
 pub struct AppProperties {
     #[serde(flatten, skip_serializing)]
     pub namespaces: Namespaces,
-    pub application: Option<Application>,
-    pub heading_pairs: Option<HeadingPairs>,
-    pub titles_of_parts: Option<TitlesOfParts>,
-    pub lines: Option<String>,
-    pub links_up_to_date: Option<String>,
-    pub local_name: Option<String>,
-    pub company: Option<String>,
     pub template: Option<String>,
     pub manager: Option<String>,
+    pub company: Option<String>,
     pub pages: Option<String>,
+    pub words: Option<String>,
+    pub characters: Option<String>,
+    pub presentation_format: Option<String>,
+    pub lines: Option<String>,
+    pub paragraphs: Option<String>,
+    pub slides: Option<String>,
+    pub notes: Option<String>,
+    pub total_time: Option<String>,
+    pub hidden_slides: Option<String>,
+    #[serde(rename = "MMClips")]
+    pub mm_clips: Option<String>,
+    pub scale_crop: Option<String>,
+    heading_pairs: Option<HeadingPairs>,
+    titles_of_parts: Option<TitlesOfParts>,
+    pub links_up_to_date: Option<String>,
+    pub characters_with_spaces: Option<String>,
+    pub shared_doc: Option<String>,
+    pub hyperlink_base: Option<String>,
+    pub hyperlinks_changed: Option<String>,
+    pub application: Option<String>,
+    pub app_version: Option<String>,
+    pub doc_security: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Application(String);
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct HeadingPairs {
+struct HeadingPairs {
     #[serde(rename = "$value")]
     variant: Variant,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct TitlesOfParts {
+struct TitlesOfParts {
     #[serde(rename(deserialize = "$value", serialize = "vt:vector"))]
     value: Variant,
 }
 
 impl AppProperties {
-    pub fn parse_from_str(reader: &str) -> Self {
-        quick_xml::de::from_str(reader).unwrap()
+    pub fn parse_from_str(reader: &str) -> Result<Self, OoxmlError> {
+        Self::from_xml_str(reader)
+    }
+
+    pub fn set_application<S: Into<String>>(&mut self, application: S) {
+        self.application = Some(application.into());
+    }
+
+    pub fn set_company<S: Into<String>>(&mut self, company: S) {
+        self.company = Some(company.into());
+    }
+
+    pub fn set_manager<S: Into<String>>(&mut self, manager: S) {
+        self.manager = Some(manager.into());
+    }
+
+    pub fn set_template<S: Into<String>>(&mut self, template: S) {
+        self.template = Some(template.into());
+    }
+
+    /// The document parts grouped by heading, e.g. `[("Worksheets", ["Sheet1", "Sheet2"])]`,
+    /// combined from the heading pairs and the titles of parts.
+    pub fn titles_of_parts(&self) -> Vec<(String, Vec<String>)> {
+        let pairs = self
+            .heading_pairs
+            .as_ref()
+            .and_then(|pairs| pairs.variant.as_slice())
+            .unwrap_or_default();
+        let mut titles = self
+            .titles_of_parts
+            .as_ref()
+            .and_then(|titles| titles.value.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|title| title.as_str().unwrap_or_default().to_string());
+        pairs
+            .chunks_exact(2)
+            .map(|pair| {
+                let heading = pair[0].as_str().unwrap_or_default().to_string();
+                let count = pair[1].as_i64().unwrap_or_default().max(0) as usize;
+                (heading, titles.by_ref().take(count).collect())
+            })
+            .collect()
+    }
+
+    /// Replace the heading pairs and titles of parts.
+    pub fn set_titles_of_parts<H, T, I>(&mut self, parts: I)
+    where
+        H: Into<String>,
+        T: Into<String>,
+        I: IntoIterator<Item = (H, Vec<T>)>,
+    {
+        let mut pairs = Vec::new();
+        let mut titles = Vec::new();
+        for (heading, part_titles) in parts {
+            let variant = |value| Variant::VtVariant {
+                value: Box::new(value),
+            };
+            pairs.push(variant(Variant::VtLpstr(heading.into())));
+            pairs.push(variant(Variant::VtI4(part_titles.len() as i32)));
+            titles.extend(part_titles.into_iter().map(|t| Variant::VtLpstr(t.into())));
+        }
+        self.heading_pairs = Some(HeadingPairs {
+            variant: Variant::VtVector {
+                size: pairs.len(),
+                base_type: "variant".into(),
+                variants: pairs,
+            },
+        });
+        self.titles_of_parts = Some(TitlesOfParts {
+            value: Variant::VtVector {
+                size: titles.len(),
+                base_type: "lpstr".into(),
+                variants: titles,
+            },
+        });
+    }
+
+    /// Serialize the properties as an extended properties part.
+    pub fn write<W: std::io::Write>(&self, writer: W) -> Result<(), OoxmlError> {
+        let mut xml = quick_xml::Writer::new(writer);
+        use quick_xml::events::*;
+
+        xml.write_event(Event::Decl(BytesDecl::new(
+            b"1.0",
+            Some(b"UTF-8"),
+            Some(b"yes"),
+        )))?;
+
+        let elem = BytesStart::borrowed_name(APP_PROPERTIES_TAG.as_bytes()).with_attributes(vec![
+            (APP_PROPERTIES_NAMESPACE_ATTRIBUTE, APP_PROPERTIES_NAMESPACE),
+            (VT_NAMESPACE_ATTRIBUTE, VT_NAMESPACE),
+        ]);
+        xml.write_event(Event::Start(elem))?;
+
+        let write_text = |xml: &mut quick_xml::Writer<W>, tag: &str, value: &Option<String>| {
+            let value = match value {
+                Some(value) => value,
+                None => return Ok(()),
+            };
+            xml.write_event(Event::Start(BytesStart::borrowed_name(tag.as_bytes())))?;
+            xml.write_event(Event::Text(BytesText::from_plain_str(value)))?;
+            xml.write_event(Event::End(BytesEnd::borrowed(tag.as_bytes())))
+        };
+        let write_variant = |xml: &mut quick_xml::Writer<W>, tag: &str, value: Option<&Variant>| {
+            let value = match value {
+                Some(value) => value,
+                None => return Ok(()),
+            };
+            xml.write_event(Event::Start(BytesStart::borrowed_name(tag.as_bytes())))?;
+            value.write(xml)?;
+            xml.write_event(Event::End(BytesEnd::borrowed(tag.as_bytes())))?;
+            Ok::<_, OoxmlError>(())
+        };
+
+        write_text(&mut xml, "Template", &self.template)?;
+        write_text(&mut xml, "Manager", &self.manager)?;
+        write_text(&mut xml, "TotalTime", &self.total_time)?;
+        write_text(&mut xml, "Pages", &self.pages)?;
+        write_text(&mut xml, "Words", &self.words)?;
+        write_text(&mut xml, "Characters", &self.characters)?;
+        write_text(&mut xml, "PresentationFormat", &self.presentation_format)?;
+        write_text(&mut xml, "Application", &self.application)?;
+        write_text(&mut xml, "DocSecurity", &self.doc_security)?;
+        write_text(&mut xml, "Lines", &self.lines)?;
+        write_text(&mut xml, "Paragraphs", &self.paragraphs)?;
+        write_text(&mut xml, "Slides", &self.slides)?;
+        write_text(&mut xml, "Notes", &self.notes)?;
+        write_text(&mut xml, "HiddenSlides", &self.hidden_slides)?;
+        write_text(&mut xml, "MMClips", &self.mm_clips)?;
+        write_text(&mut xml, "ScaleCrop", &self.scale_crop)?;
+        let heading_pairs = self.heading_pairs.as_ref().map(|pairs| &pairs.variant);
+        write_variant(&mut xml, "HeadingPairs", heading_pairs)?;
+        let titles_of_parts = self.titles_of_parts.as_ref().map(|titles| &titles.value);
+        write_variant(&mut xml, "TitlesOfParts", titles_of_parts)?;
+        write_text(&mut xml, "Company", &self.company)?;
+        write_text(&mut xml, "LinksUpToDate", &self.links_up_to_date)?;
+        write_text(
+            &mut xml,
+            "CharactersWithSpaces",
+            &self.characters_with_spaces,
+        )?;
+        write_text(&mut xml, "SharedDoc", &self.shared_doc)?;
+        write_text(&mut xml, "HyperlinkBase", &self.hyperlink_base)?;
+        write_text(&mut xml, "HyperlinksChanged", &self.hyperlinks_changed)?;
+        write_text(&mut xml, "AppVersion", &self.app_version)?;
+
+        xml.write_event(Event::End(BytesEnd::borrowed(
+            APP_PROPERTIES_TAG.as_bytes(),
+        )))?;
+        Ok(())
     }
 }
 
-impl OpenXmlDeserializeDefault for AppProperties {}
+impl OpenXmlDeserializeDefault for AppProperties {
+    const UNDERSTOOD_NAMESPACES: &'static [&'static str] = &[VT_NAMESPACE];
+}
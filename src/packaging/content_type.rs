@@ -1,9 +1,11 @@
 use std::fmt::Display;
 
 use linked_hash_map::LinkedHashMap;
-use serde::de::{Deserialize, MapAccess, Visitor};
+use serde::de::{Deserialize, Error, IgnoredAny, MapAccess, Visitor};
 
 use crate::error::OoxmlError;
+use crate::packaging::element::{OpenXmlDeserializeDefault, OpenXmlDeserialized};
+use crate::packaging::uri::PackUri;
 
 pub type ContentType = String;
 
@@ -16,8 +18,34 @@ pub const OVERRIDE_TAG_NAME: &'static str = "Override";
 pub const PART_NAME_ATTRIBUTE_NAME: &'static str = "PartName";
 pub const EXTENSION_ATTRIBUTE_NAME: &'static str = "Extension";
 pub const CONTENT_TYPE_ATTRIBUTE_NAME: &'static str = "ContentType";
+/// Elements modelled by [`ContentTypes`], others are kept when the part is rewritten.
+pub(crate) const CONTENT_TYPES_TAGS: &[&str] = &[DEFAULT_TAG_NAME, OVERRIDE_TAG_NAME];
 pub const XMLNS_ATTRIBUTE_NAME: &'static str = "xmlns";
 
+/// Whether two content types are equal.
+///
+/// The type, subtype and parameter names are compared case-insensitively (RFC 7231,
+/// 3.1.1.1), parameter values as they are.
+pub fn content_type_eq(a: &str, b: &str) -> bool {
+    let mut a = a.split(';').map(str::trim);
+    let mut b = b.split(';').map(str::trim);
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return true,
+            (Some(a), Some(b)) => {
+                let (a_name, a_value) = a.split_once('=').unwrap_or((a, ""));
+                let (b_name, b_value) = b.split_once('=').unwrap_or((b, ""));
+                if !a_name.trim().eq_ignore_ascii_case(b_name.trim())
+                    || a_value.trim() != b_value.trim()
+                {
+                    return false;
+                }
+            }
+            _ => return false,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct ContentTypes {
     defaults: LinkedHashMap<String, ContentType>,
@@ -49,8 +77,8 @@ impl Display for ContentTypes {
     }
 }
 impl ContentTypes {
-    pub fn parse_from_xml_str(reader: &str) -> Self {
-        quick_xml::de::from_str(reader).unwrap()
+    pub fn parse_from_xml_str(reader: &str) -> Result<Self, OoxmlError> {
+        Self::from_xml_str(reader).map_err(|e| e.in_part(CONTENT_TYPES_FILE))
     }
 
     pub fn add_default_element(&mut self, extension: String, content_type: ContentType) {
@@ -61,6 +89,16 @@ impl ContentTypes {
         self.overrides.insert(part_name, content_type);
     }
 
+    /// Remove the override of `part`, comparing part names as pack URIs.
+    pub fn remove_override_element(&mut self, part: &PackUri) -> Option<ContentType> {
+        let part_name = self
+            .overrides
+            .keys()
+            .find(|part_name| PackUri::new(part_name) == *part)?
+            .clone();
+        self.overrides.remove(&part_name)
+    }
+
     pub fn write<W: std::io::Write>(&self, writer: W) -> Result<(), OoxmlError> {
         let mut xml = quick_xml::Writer::new(writer);
         use quick_xml::events::attributes::Attribute;
@@ -123,8 +161,35 @@ impl ContentTypes {
     pub fn is_empty(&self) -> bool {
         self.defaults.is_empty() && self.overrides.is_empty()
     }
+
+    /// Get the content type of `part`: the override for the part name if any, otherwise
+    /// the default for its extension. Both are matched case-insensitively.
+    pub fn get_content_type(&self, part: &PackUri) -> Option<&ContentType> {
+        self.overrides
+            .iter()
+            .find(|(part_name, _)| PackUri::new(part_name) == *part)
+            .map(|(_, content_type)| content_type)
+            .or_else(|| self.get_default_content_type(part.extension()?))
+    }
+
+    pub fn get_default_content_type(&self, extension: &str) -> Option<&ContentType> {
+        self.defaults
+            .iter()
+            .find(|(ext, _)| ext.eq_ignore_ascii_case(extension))
+            .map(|(_, content_type)| content_type)
+    }
+
+    pub fn defaults(&self) -> impl Iterator<Item = (&String, &ContentType)> {
+        self.defaults.iter()
+    }
+
+    pub fn overrides(&self) -> impl Iterator<Item = (&String, &ContentType)> {
+        self.overrides.iter()
+    }
 }
 
+impl OpenXmlDeserializeDefault for ContentTypes {}
+
 struct ContentTypesVisitor;
 
 impl<'de> Visitor<'de> for ContentTypesVisitor {
@@ -146,7 +211,7 @@ impl<'de> Visitor<'de> for ContentTypesVisitor {
                     let _xmlns: String = access.next_value()?;
                 }
                 s if s == TYPES_TAG_NAME => {
-                    unreachable!();
+                    return Err(M::Error::custom("unexpected nested Types element"));
                 }
                 s if s == DEFAULT_TAG_NAME => {
                     let v: Default = access.next_value()?;
@@ -157,7 +222,8 @@ impl<'de> Visitor<'de> for ContentTypesVisitor {
                     types.add_override_element(v.part_name, v.content_type);
                 }
                 _ => {
-                    unreachable!("content type unsupport!");
+                    log::debug!("unrecognized content types element: {}", key);
+                    let _: IgnoredAny = access.next_value()?;
                 }
             }
         }
@@ -187,3 +253,24 @@ fn test_de() {
     let display = format!("{}", content_types);
     assert_eq!(raw, display);
 }
+
+#[test]
+fn test_de_unknown_element() {
+    let raw = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="xml" ContentType="application/xml"/><Unknown Foo="bar"/></Types>"#;
+    let content_types = ContentTypes::parse_from_xml_str(raw).unwrap();
+    assert_eq!(content_types.defaults().count(), 1);
+
+    let raw = r#"<Types><Default Extension="xml"/></Types>"#;
+    assert!(ContentTypes::parse_from_xml_str(raw).is_err());
+}
+
+#[test]
+fn test_content_type_eq() {
+    assert!(content_type_eq("image/png", "Image/PNG"));
+    assert!(content_type_eq(
+        "text/xml; charset=utf-8",
+        "TEXT/XML;Charset=utf-8"
+    ));
+    assert!(!content_type_eq("text/xml; charset=utf-8", "text/xml"));
+    assert!(!content_type_eq("image/png", "image/jpeg"));
+}
@@ -1,8 +1,12 @@
+use crate::error::OoxmlError;
+use crate::packaging::element::{OpenXmlDeserializeDefault, OpenXmlDeserialized};
 use crate::packaging::{namespace::Namespaces, variant::Variant};
 
 use serde::{Deserialize, Serialize};
 
 pub const CUSTOM_PROPERTIES_URI: &str = "docProps/custom.xml";
+pub const CUSTOM_PROPERTIES_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.custom-properties+xml";
 
 pub const CUSTOM_PROPERTIES_TAG: &str = "Properties";
 pub const CUSTOM_PROPERTIES_NAMESPACE_ATTRIBUTE: &str = "xmlns";
@@ -11,6 +15,11 @@ pub const CUSTOM_PROPERTIES_NAMESPACE: &str =
 
 pub const CUSTOM_PROPERTY_TAG: &str = "property";
 
+/// Format id of user defined custom properties (FMTID_UserDefinedProperties).
+pub const USER_DEFINED_PROPERTIES_FMTID: &str = "{D5CDD505-2E9C-101B-9397-08002B2CF9AE}";
+/// Property ids 0 and 1 are reserved, user defined properties start at 2.
+const FIRST_PROPERTY_ID: u32 = 2;
+
 pub const VT_NAMESPACE_ATTRIBUTE: &str = "xmlns:vt";
 pub const VT_NAMESPACE: &str =
     "http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes";
@@ -20,7 +29,7 @@ pub const VT_NAMESPACE: &str =
 pub struct CustomProperties {
     #[serde(flatten)]
     namespaces: Namespaces,
-    #[serde(rename = "property")]
+    #[serde(rename = "property", default)]
     properties: Vec<CustomProperty>,
 }
 
@@ -34,8 +43,151 @@ pub struct CustomProperty {
     value: Variant,
 }
 
+impl CustomProperty {
+    pub fn value(&self) -> &Variant {
+        &self.value
+    }
+}
+
 impl CustomProperties {
-    pub fn parse_from_xml_str(reader: &str) -> Self {
-        quick_xml::de::from_str(reader).unwrap()
+    pub fn parse_from_xml_str(reader: &str) -> Result<Self, OoxmlError> {
+        Self::from_xml_str(reader)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Variant> {
+        self.get_property(name).map(CustomProperty::value)
+    }
+
+    pub fn get_property(&self, name: &str) -> Option<&CustomProperty> {
+        self.properties
+            .iter()
+            .find(|property| property.name.eq_ignore_ascii_case(name))
     }
+
+    /// Iterate the properties as name and value pairs in document order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Variant)> {
+        self.properties
+            .iter()
+            .map(|property| (property.name.as_str(), &property.value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.properties.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.properties.is_empty()
+    }
+
+    /// Set the value of the property `name`. Names are matched case-insensitively, as
+    /// Office does.
+    ///
+    /// A new property gets the user defined format id and the next unused property id.
+    /// Fails with [`OoxmlError::PropertyIdsExhausted`] when the largest property id is
+    /// already in use.
+    pub fn set<S: Into<String>, V: Into<Variant>>(
+        &mut self,
+        name: S,
+        value: V,
+    ) -> Result<(), OoxmlError> {
+        let name = name.into();
+        let value = value.into();
+        if let Some(property) = self
+            .properties
+            .iter_mut()
+            .find(|p| p.name.eq_ignore_ascii_case(&name))
+        {
+            property.value = value;
+            return Ok(());
+        }
+        let mut pid = FIRST_PROPERTY_ID;
+        for property in &self.properties {
+            if let Ok(used) = property.pid.parse::<u32>() {
+                let next = used
+                    .checked_add(1)
+                    .ok_or_else(|| OoxmlError::PropertyIdsExhausted(name.clone()))?;
+                pid = pid.max(next);
+            }
+        }
+        self.properties.push(CustomProperty {
+            fmtid: USER_DEFINED_PROPERTIES_FMTID.to_string(),
+            pid: pid.to_string(),
+            name,
+            value,
+        });
+        Ok(())
+    }
+
+    /// Remove the property `name`, returning its value.
+    pub fn remove(&mut self, name: &str) -> Option<Variant> {
+        let index = self
+            .properties
+            .iter()
+            .position(|p| p.name.eq_ignore_ascii_case(name))?;
+        Some(self.properties.remove(index).value)
+    }
+
+    /// Serialize the properties as a custom properties part.
+    pub fn write<W: std::io::Write>(&self, writer: W) -> Result<(), OoxmlError> {
+        let mut xml = quick_xml::Writer::new(writer);
+        use quick_xml::events::*;
+
+        xml.write_event(Event::Decl(BytesDecl::new(
+            b"1.0",
+            Some(b"UTF-8"),
+            Some(b"yes"),
+        )))?;
+
+        let elem =
+            BytesStart::borrowed_name(CUSTOM_PROPERTIES_TAG.as_bytes()).with_attributes(vec![
+                (
+                    CUSTOM_PROPERTIES_NAMESPACE_ATTRIBUTE,
+                    CUSTOM_PROPERTIES_NAMESPACE,
+                ),
+                (VT_NAMESPACE_ATTRIBUTE, VT_NAMESPACE),
+            ]);
+        xml.write_event(Event::Start(elem))?;
+
+        for property in &self.properties {
+            let elem =
+                BytesStart::borrowed_name(CUSTOM_PROPERTY_TAG.as_bytes()).with_attributes(vec![
+                    ("fmtid", property.fmtid.as_str()),
+                    ("pid", property.pid.as_str()),
+                    ("name", property.name.as_str()),
+                ]);
+            xml.write_event(Event::Start(elem))?;
+            property.value.write(&mut xml)?;
+            xml.write_event(Event::End(BytesEnd::borrowed(
+                CUSTOM_PROPERTY_TAG.as_bytes(),
+            )))?;
+        }
+
+        xml.write_event(Event::End(BytesEnd::borrowed(
+            CUSTOM_PROPERTIES_TAG.as_bytes(),
+        )))?;
+        Ok(())
+    }
+}
+
+impl OpenXmlDeserializeDefault for CustomProperties {
+    const UNDERSTOOD_NAMESPACES: &'static [&'static str] = &[VT_NAMESPACE];
+}
+
+#[test]
+fn set_custom_properties() {
+    let raw = r#"<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/custom-properties" xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes"><property fmtid="{D5CDD505-2E9C-101B-9397-08002B2CF9AE}" pid="2" name="Project"><vt:lpwstr>A</vt:lpwstr></property></Properties>"#;
+    let mut properties = CustomProperties::parse_from_xml_str(raw).unwrap();
+    properties.set("PROJECT", "B").unwrap();
+    assert_eq!(properties.len(), 1);
+    assert_eq!(
+        properties.get("project").and_then(Variant::as_str),
+        Some("B")
+    );
+
+    let raw = raw.replace(r#"pid="2""#, r#"pid="4294967295""#);
+    let mut properties = CustomProperties::parse_from_xml_str(&raw).unwrap();
+    assert!(matches!(
+        properties.set("Other", 1),
+        Err(OoxmlError::PropertyIdsExhausted(name)) if name == "Other"
+    ));
 }
@@ -1,6 +1,8 @@
 use std::io::BufRead;
 
 use crate::error::OoxmlError;
+use crate::packaging::mce;
+use crate::packaging::part::OpenXmlPart;
 
 pub trait OpenXmlDeserialized: Sized {
     fn from_xml_reader<R: BufRead>(reader: R) -> Result<Self, OoxmlError>;
@@ -8,12 +10,36 @@ pub trait OpenXmlDeserialized: Sized {
     fn from_xml_str(s: &str) -> Result<Self, OoxmlError> {
         Self::from_xml_reader(s.as_bytes())
     }
+
+    /// Parse the data of a package part, errors carry the part name.
+    fn from_part(part: &OpenXmlPart) -> Result<Self, OoxmlError> {
+        Self::from_xml_reader(part.data()?).map_err(|e| e.in_part(part.name().as_str()))
+    }
 }
 
-pub trait OpenXmlDeserializeDefault: serde::de::DeserializeOwned {}
+pub trait OpenXmlDeserializeDefault: serde::de::DeserializeOwned {
+    /// Namespaces the type models besides the namespace of the document element, e.g. the
+    /// relationships namespace of `r:id` attributes.
+    ///
+    /// Markup compatibility content is resolved against these and the document element
+    /// namespace before deserializing.
+    const UNDERSTOOD_NAMESPACES: &'static [&'static str] = &[];
+}
 
 impl<T: OpenXmlDeserializeDefault> OpenXmlDeserialized for T {
-    fn from_xml_reader<R: BufRead>(reader: R) -> Result<Self, OoxmlError> {
-        Ok(quick_xml::de::from_reader(reader)?)
+    fn from_xml_reader<R: BufRead>(mut reader: R) -> Result<Self, OoxmlError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let data = mce::preprocess(&data, T::UNDERSTOOD_NAMESPACES)?;
+        deserialize(data.as_slice())
     }
 }
+
+fn deserialize<T: serde::de::DeserializeOwned, R: BufRead>(reader: R) -> Result<T, OoxmlError> {
+    let mut de = quick_xml::de::Deserializer::from_reader(reader);
+    serde_path_to_error::deserialize(&mut de).map_err(|e| OoxmlError::PartParseError {
+        part: String::new(),
+        path: e.path().to_string(),
+        source: e.into_inner(),
+    })
+}
@@ -0,0 +1,787 @@
+//! Password protected packages (MS-OFFCRYPTO).
+//!
+//! An encrypted package is an OLE compound file with an `EncryptionInfo` stream, which
+//! describes how the key is derived from the password, and an `EncryptedPackage` stream,
+//! which holds the encrypted zip package.
+
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha1::Sha1;
+use sha2::{Sha256, Sha384, Sha512};
+
+use crate::error::OoxmlError;
+use crate::packaging::signature::DigestAlgorithm;
+use crate::packaging::xml::XmlElement;
+
+const CFB_SIGNATURE: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+const ENCRYPTION_INFO_STREAM: &str = "/EncryptionInfo";
+const ENCRYPTED_PACKAGE_STREAM: &str = "/EncryptedPackage";
+
+/// Agile encryption encrypts the package in segments of this size.
+const SEGMENT_LENGTH: usize = 4096;
+const AES_BLOCK_SIZE: usize = 16;
+
+const VERIFIER_HASH_INPUT_BLOCK_KEY: [u8; 8] = [0xfe, 0xa7, 0xd2, 0x76, 0x3b, 0x4b, 0x9e, 0x79];
+const VERIFIER_HASH_VALUE_BLOCK_KEY: [u8; 8] = [0xd7, 0xaa, 0x0f, 0x6d, 0x30, 0x61, 0x34, 0x4e];
+const KEY_VALUE_BLOCK_KEY: [u8; 8] = [0x14, 0x6e, 0x0b, 0xe7, 0xab, 0xac, 0xd0, 0xd6];
+const HMAC_KEY_BLOCK_KEY: [u8; 8] = [0x5f, 0xb2, 0xad, 0x01, 0x0c, 0xb9, 0xe1, 0xf6];
+const HMAC_VALUE_BLOCK_KEY: [u8; 8] = [0xa0, 0x67, 0x7f, 0x02, 0xb2, 0x2c, 0x84, 0x33];
+
+const ENCRYPTION_NAMESPACE_URI: &str = "http://schemas.microsoft.com/office/2006/encryption";
+const PASSWORD_KEY_ENCRYPTOR_URI: &str =
+    "http://schemas.microsoft.com/office/2006/keyEncryptor/password";
+const CERTIFICATE_KEY_ENCRYPTOR_URI: &str =
+    "http://schemas.microsoft.com/office/2006/keyEncryptor/certificate";
+/// Spin count and key size used by Office when encrypting.
+const AGILE_SPIN_COUNT: u32 = 100_000;
+const AGILE_KEY_BYTES: usize = 32;
+const AGILE_SALT_SIZE: usize = 16;
+/// Largest spin count a password key encryptor may use (MS-OFFCRYPTO 2.3.4.11).
+const MAX_SPIN_COUNT: u32 = 10_000_000;
+
+const DATA_SPACES_STORAGE: &str = "/\u{6}DataSpaces";
+const ENCRYPTION_TRANSFORM_ID: &str = "{FF9A3F03-56EF-4613-BDD5-5A41C1D07246}";
+
+const STANDARD_SPIN_COUNT: u32 = 50_000;
+const STANDARD_VERIFIER_HASH_SIZE: usize = 20;
+const STANDARD_FLAG_CRYPTO_API: u32 = 0x04;
+const STANDARD_FLAG_AES: u32 = 0x20;
+
+/// Whether `reader` starts with the OLE compound file signature. The reader position is
+/// restored.
+fn is_compound_file<R: Read + Seek>(reader: &mut R) -> Result<bool, OoxmlError> {
+    let position = reader.stream_position()?;
+    let mut signature = [0u8; 8];
+    let is_compound_file = match reader.read_exact(&mut signature) {
+        Ok(()) => signature == CFB_SIGNATURE,
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => false,
+        Err(e) => return Err(e.into()),
+    };
+    reader.seek(SeekFrom::Start(position))?;
+    Ok(is_compound_file)
+}
+
+/// Whether `reader` is a compound file holding an encrypted package, as opposed to e.g. a
+/// legacy `.xls` workbook. The reader position is restored.
+pub(crate) fn is_encrypted_package<R: Read + Seek>(reader: &mut R) -> Result<bool, OoxmlError> {
+    if !is_compound_file(reader)? {
+        return Ok(false);
+    }
+    let position = reader.stream_position()?;
+    let is_encrypted_package = match cfb::CompoundFile::open(&mut *reader) {
+        Ok(compound_file) => {
+            compound_file.is_stream(ENCRYPTION_INFO_STREAM)
+                && compound_file.is_stream(ENCRYPTED_PACKAGE_STREAM)
+        }
+        Err(_) => false,
+    };
+    reader.seek(SeekFrom::Start(position))?;
+    Ok(is_encrypted_package)
+}
+
+/// Decrypt the zip package of an encrypted compound file.
+///
+/// Fails with [`OoxmlError::PackageTooLarge`] before decrypting when the package is
+/// larger than `max_size` bytes.
+pub(crate) fn decrypt<R: Read + Seek>(
+    reader: R,
+    password: &str,
+    max_size: Option<u64>,
+) -> Result<Vec<u8>, OoxmlError> {
+    let mut compound_file = cfb::CompoundFile::open(reader)?;
+    let mut encryption_info = Vec::new();
+    compound_file
+        .open_stream(ENCRYPTION_INFO_STREAM)?
+        .read_to_end(&mut encryption_info)?;
+    let mut encrypted_package = Vec::new();
+    compound_file
+        .open_stream(ENCRYPTED_PACKAGE_STREAM)?
+        .read_to_end(&mut encrypted_package)?;
+    let size = package_size(&encrypted_package)?;
+    if let Some(limit) = max_size.filter(|&limit| size as u64 > limit) {
+        return Err(OoxmlError::PackageTooLarge { limit });
+    }
+
+    let version = (
+        read_u16(&encryption_info, 0)?,
+        read_u16(&encryption_info, 2)?,
+    );
+    match version {
+        (4, 4) => {
+            let xml = encryption_info
+                .get(8..)
+                .ok_or_else(|| malformed("encryption info is truncated"))?;
+            AgileEncryption::parse(xml)?.decrypt(password, &encrypted_package)
+        }
+        (2..=4, 2) => {
+            StandardEncryption::parse(&encryption_info)?.decrypt(password, &encrypted_package)
+        }
+        (major, minor) => Err(unsupported(format!(
+            "encryption version {}.{}",
+            major, minor
+        ))),
+    }
+}
+
+/// Encrypt a zip package with `password` using Agile encryption with AES-256 and SHA-512,
+/// returning the compound file.
+pub(crate) fn encrypt(package: &[u8], password: &str) -> Result<Vec<u8>, OoxmlError> {
+    let (encryption_info, encrypted_package) = AgileEncryption::encrypt(package, password)?;
+
+    let mut compound_file = cfb::CompoundFile::create(Cursor::new(Vec::new()))?;
+    write_data_spaces(&mut compound_file)?;
+    compound_file
+        .create_stream(ENCRYPTION_INFO_STREAM)?
+        .write_all(&encryption_info)?;
+    compound_file
+        .create_stream(ENCRYPTED_PACKAGE_STREAM)?
+        .write_all(&encrypted_package)?;
+    compound_file.flush()?;
+    Ok(compound_file.into_inner().into_inner())
+}
+
+/// Write the `\x06DataSpaces` storage which declares that `EncryptedPackage` is
+/// transformed by the strong encryption transform (MS-OFFCRYPTO 2.1).
+fn write_data_spaces<F: Read + Write + Seek>(
+    compound_file: &mut cfb::CompoundFile<F>,
+) -> Result<(), OoxmlError> {
+    let version = |data: &mut Vec<u8>| {
+        for _ in 0..3 {
+            data.extend(1u16.to_le_bytes());
+            data.extend(0u16.to_le_bytes());
+        }
+    };
+
+    let mut version_info = Vec::new();
+    write_unicode(&mut version_info, "Microsoft.Container.DataSpaces");
+    version(&mut version_info);
+
+    let mut entry = Vec::new();
+    entry.extend(1u32.to_le_bytes());
+    entry.extend(0u32.to_le_bytes());
+    write_unicode(&mut entry, "EncryptedPackage");
+    write_unicode(&mut entry, "StrongEncryptionDataSpace");
+    let mut data_space_map = Vec::new();
+    data_space_map.extend(8u32.to_le_bytes());
+    data_space_map.extend(1u32.to_le_bytes());
+    data_space_map.extend((entry.len() as u32 + 4).to_le_bytes());
+    data_space_map.extend(entry);
+
+    let mut data_space_definition = Vec::new();
+    data_space_definition.extend(8u32.to_le_bytes());
+    data_space_definition.extend(1u32.to_le_bytes());
+    write_unicode(&mut data_space_definition, "StrongEncryptionTransform");
+
+    let mut transform_id = Vec::new();
+    write_unicode(&mut transform_id, ENCRYPTION_TRANSFORM_ID);
+    let mut transform_info = Vec::new();
+    transform_info.extend((transform_id.len() as u32 + 8).to_le_bytes());
+    transform_info.extend(1u32.to_le_bytes());
+    transform_info.extend(transform_id);
+    write_unicode(
+        &mut transform_info,
+        "Microsoft.Container.EncryptionTransform",
+    );
+    version(&mut transform_info);
+    // Empty encryption name, block size, cipher mode and reserved value.
+    for value in [0u32, 0, 0, 4] {
+        transform_info.extend(value.to_le_bytes());
+    }
+
+    let storage = DATA_SPACES_STORAGE;
+    compound_file.create_storage(storage)?;
+    compound_file.create_storage(format!("{}/DataSpaceInfo", storage))?;
+    compound_file.create_storage(format!("{}/TransformInfo", storage))?;
+    compound_file.create_storage(format!(
+        "{}/TransformInfo/StrongEncryptionTransform",
+        storage
+    ))?;
+    let streams = [
+        (format!("{}/Version", storage), version_info),
+        (format!("{}/DataSpaceMap", storage), data_space_map),
+        (
+            format!("{}/DataSpaceInfo/StrongEncryptionDataSpace", storage),
+            data_space_definition,
+        ),
+        (
+            format!(
+                "{}/TransformInfo/StrongEncryptionTransform/\u{6}Primary",
+                storage
+            ),
+            transform_info,
+        ),
+    ];
+    for (path, data) in streams.iter() {
+        compound_file.create_stream(path)?.write_all(data)?;
+    }
+    Ok(())
+}
+
+/// Write a length prefixed UTF-16 string padded to 4 bytes (UNICODE-LP-P4).
+fn write_unicode(data: &mut Vec<u8>, value: &str) {
+    let encoded: Vec<u8> = value.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    data.extend((encoded.len() as u32).to_le_bytes());
+    data.extend(&encoded);
+    data.resize(data.len() + (4 - encoded.len() % 4) % 4, 0);
+}
+
+/// Agile encryption (MS-OFFCRYPTO 2.3.4.10), used by Office 2010 and later.
+struct AgileEncryption {
+    key_data: KeyParameters,
+    encrypted_hmac_key: Option<Vec<u8>>,
+    encrypted_hmac_value: Option<Vec<u8>>,
+    password_key: KeyParameters,
+    spin_count: u32,
+    encrypted_verifier_hash_input: Vec<u8>,
+    encrypted_verifier_hash_value: Vec<u8>,
+    encrypted_key_value: Vec<u8>,
+}
+
+/// Cipher and hash parameters of `keyData` and of the password key encryptor.
+struct KeyParameters {
+    salt: Vec<u8>,
+    block_size: usize,
+    key_bytes: usize,
+    hash: DigestAlgorithm,
+}
+
+impl KeyParameters {
+    fn parse(element: &XmlElement) -> Result<Self, OoxmlError> {
+        let cipher = required(element, "cipherAlgorithm")?;
+        let chaining = required(element, "cipherChaining")?;
+        if cipher != "AES" || chaining != "ChainingModeCBC" {
+            return Err(unsupported(format!("cipher {} {}", cipher, chaining)));
+        }
+        let block_size = parse_number(element, "blockSize")?;
+        if block_size != AES_BLOCK_SIZE {
+            return Err(malformed("AES block size is not 16 bytes"));
+        }
+        let key_bits: usize = parse_number(element, "keyBits")?;
+        if ![128, 192, 256].contains(&key_bits) {
+            return Err(unsupported(format!("key size of {} bits", key_bits)));
+        }
+        let hash = required(element, "hashAlgorithm")?;
+        Ok(Self {
+            salt: decode_base64(required(element, "saltValue")?)?,
+            block_size,
+            key_bytes: key_bits / 8,
+            hash: hash_algorithm(hash).ok_or_else(|| unsupported(format!("hash {}", hash)))?,
+        })
+    }
+
+    fn write_attributes(&self, element: &mut XmlElement) {
+        element.set_attribute("saltSize", self.salt.len().to_string());
+        element.set_attribute("blockSize", self.block_size.to_string());
+        element.set_attribute("keyBits", (self.key_bytes * 8).to_string());
+        element.set_attribute("hashSize", self.hash.digest(&[]).len().to_string());
+        element.set_attribute("cipherAlgorithm", "AES");
+        element.set_attribute("cipherChaining", "ChainingModeCBC");
+        element.set_attribute("hashAlgorithm", hash_name(self.hash));
+        element.set_attribute("saltValue", BASE64.encode(&self.salt));
+    }
+
+    /// Truncate or pad `value` with `0x36` to `length` bytes (MS-OFFCRYPTO 2.3.4.12).
+    fn fit(value: &[u8], length: usize) -> Vec<u8> {
+        let mut value = value[..value.len().min(length)].to_vec();
+        value.resize(length, 0x36);
+        value
+    }
+
+    /// Initialization vector for `block_key`, or the salt if there is no block key.
+    fn iv(&self, block_key: Option<&[u8]>) -> Vec<u8> {
+        match block_key {
+            Some(block_key) => {
+                let hash = self
+                    .hash
+                    .digest(&[self.salt.as_slice(), block_key].concat());
+                Self::fit(&hash, self.block_size)
+            }
+            None => Self::fit(&self.salt, self.block_size),
+        }
+    }
+}
+
+impl AgileEncryption {
+    fn parse(xml: &[u8]) -> Result<Self, OoxmlError> {
+        let encryption = XmlElement::parse(xml)?;
+        let key_data = encryption
+            .child("keyData")
+            .ok_or_else(|| malformed("keyData is missing"))?;
+        let data_integrity = encryption.child("dataIntegrity");
+        let password_key = encryption
+            .child("keyEncryptors")
+            .into_iter()
+            .flat_map(|encryptors| encryptors.children_named("keyEncryptor"))
+            .filter_map(|encryptor| encryptor.child("encryptedKey"))
+            .find(|key| key.attribute("spinCount").is_some())
+            .ok_or_else(|| unsupported("key encryptor without password".to_string()))?;
+
+        let spin_count = parse_number(password_key, "spinCount")?;
+        if spin_count > MAX_SPIN_COUNT {
+            return Err(malformed("spin count is larger than 10,000,000"));
+        }
+
+        let optional_base64 = |name: &str| {
+            data_integrity
+                .and_then(|element| element.attribute(name))
+                .map(decode_base64)
+                .transpose()
+        };
+        Ok(Self {
+            key_data: KeyParameters::parse(key_data)?,
+            encrypted_hmac_key: optional_base64("encryptedHmacKey")?,
+            encrypted_hmac_value: optional_base64("encryptedHmacValue")?,
+            password_key: KeyParameters::parse(password_key)?,
+            spin_count,
+            encrypted_verifier_hash_input: decode_base64(required(
+                password_key,
+                "encryptedVerifierHashInput",
+            )?)?,
+            encrypted_verifier_hash_value: decode_base64(required(
+                password_key,
+                "encryptedVerifierHashValue",
+            )?)?,
+            encrypted_key_value: decode_base64(required(password_key, "encryptedKeyValue")?)?,
+        })
+    }
+
+    /// Encrypt `package`, returning the `EncryptionInfo` and `EncryptedPackage` streams.
+    fn encrypt(package: &[u8], password: &str) -> Result<(Vec<u8>, Vec<u8>), OoxmlError> {
+        let hash = DigestAlgorithm::Sha512;
+        let key_data = KeyParameters {
+            salt: random_bytes(AGILE_SALT_SIZE),
+            block_size: AES_BLOCK_SIZE,
+            key_bytes: AGILE_KEY_BYTES,
+            hash,
+        };
+        let password_key = KeyParameters {
+            salt: random_bytes(AGILE_SALT_SIZE),
+            ..key_data
+        };
+        let key = random_bytes(AGILE_KEY_BYTES);
+
+        let password_hash = password_hash(hash, &password_key.salt, password, AGILE_SPIN_COUNT);
+        let encrypt_with = |block_key: &[u8], data: &[u8]| {
+            let derived = hash.digest(&[password_hash.as_slice(), block_key].concat());
+            let derived = KeyParameters::fit(&derived, AGILE_KEY_BYTES);
+            encrypt_cbc(&derived, &password_key.iv(None), data)
+        };
+        let verifier_input = random_bytes(AGILE_SALT_SIZE);
+        let encrypted_verifier_hash_input =
+            encrypt_with(&VERIFIER_HASH_INPUT_BLOCK_KEY, &verifier_input)?;
+        let encrypted_verifier_hash_value = encrypt_with(
+            &VERIFIER_HASH_VALUE_BLOCK_KEY,
+            &hash.digest(&verifier_input),
+        )?;
+        let encrypted_key_value = encrypt_with(&KEY_VALUE_BLOCK_KEY, &key)?;
+
+        let mut encrypted_package = (package.len() as u64).to_le_bytes().to_vec();
+        for (index, segment) in package.chunks(SEGMENT_LENGTH).enumerate() {
+            let iv = key_data.iv(Some(&(index as u32).to_le_bytes()));
+            encrypted_package.extend(encrypt_cbc(&key, &iv, segment)?);
+        }
+
+        let hmac_key = random_bytes(hash.digest(&[]).len());
+        let hmac_value = hmac(hash, &hmac_key, &encrypted_package);
+        let encrypted_hmac_key =
+            encrypt_cbc(&key, &key_data.iv(Some(&HMAC_KEY_BLOCK_KEY)), &hmac_key)?;
+        let encrypted_hmac_value =
+            encrypt_cbc(&key, &key_data.iv(Some(&HMAC_VALUE_BLOCK_KEY)), &hmac_value)?;
+
+        let encryption = Self {
+            key_data,
+            encrypted_hmac_key: Some(encrypted_hmac_key),
+            encrypted_hmac_value: Some(encrypted_hmac_value),
+            password_key,
+            spin_count: AGILE_SPIN_COUNT,
+            encrypted_verifier_hash_input,
+            encrypted_verifier_hash_value,
+            encrypted_key_value,
+        };
+        let mut encryption_info = Vec::new();
+        encryption_info.extend(4u16.to_le_bytes());
+        encryption_info.extend(4u16.to_le_bytes());
+        encryption_info.extend(0x40u32.to_le_bytes());
+        encryption_info.extend(encryption.to_xml().into_bytes());
+        Ok((encryption_info, encrypted_package))
+    }
+
+    fn to_xml(&self) -> String {
+        let mut encryption = XmlElement::new("encryption");
+        encryption.set_attribute("xmlns", ENCRYPTION_NAMESPACE_URI);
+        encryption.set_attribute("xmlns:p", PASSWORD_KEY_ENCRYPTOR_URI);
+        encryption.set_attribute("xmlns:c", CERTIFICATE_KEY_ENCRYPTOR_URI);
+        self.key_data
+            .write_attributes(encryption.push_child(XmlElement::new("keyData")));
+        let data_integrity = encryption.push_child(XmlElement::new("dataIntegrity"));
+        if let (Some(key), Some(value)) = (&self.encrypted_hmac_key, &self.encrypted_hmac_value) {
+            data_integrity.set_attribute("encryptedHmacKey", BASE64.encode(key));
+            data_integrity.set_attribute("encryptedHmacValue", BASE64.encode(value));
+        }
+        let key_encryptor = encryption
+            .push_child(XmlElement::new("keyEncryptors"))
+            .push_child(XmlElement::new("keyEncryptor"));
+        key_encryptor.set_attribute("uri", PASSWORD_KEY_ENCRYPTOR_URI);
+        let encrypted_key = key_encryptor.push_child(XmlElement::new("p:encryptedKey"));
+        encrypted_key.set_attribute("spinCount", self.spin_count.to_string());
+        self.password_key.write_attributes(encrypted_key);
+        encrypted_key.set_attribute(
+            "encryptedVerifierHashInput",
+            BASE64.encode(&self.encrypted_verifier_hash_input),
+        );
+        encrypted_key.set_attribute(
+            "encryptedVerifierHashValue",
+            BASE64.encode(&self.encrypted_verifier_hash_value),
+        );
+        encrypted_key.set_attribute(
+            "encryptedKeyValue",
+            BASE64.encode(&self.encrypted_key_value),
+        );
+        encryption.to_xml()
+    }
+
+    fn decrypt(&self, password: &str, encrypted_package: &[u8]) -> Result<Vec<u8>, OoxmlError> {
+        let params = &self.password_key;
+        let password_hash = password_hash(params.hash, &params.salt, password, self.spin_count);
+        let decrypt_with = |block_key: &[u8], data: &[u8]| {
+            let key = params
+                .hash
+                .digest(&[password_hash.as_slice(), block_key].concat());
+            let key = KeyParameters::fit(&key, params.key_bytes);
+            decrypt_cbc(&key, &params.iv(None), data)
+        };
+
+        let verifier_input = decrypt_with(
+            &VERIFIER_HASH_INPUT_BLOCK_KEY,
+            &self.encrypted_verifier_hash_input,
+        )?;
+        let verifier_hash = decrypt_with(
+            &VERIFIER_HASH_VALUE_BLOCK_KEY,
+            &self.encrypted_verifier_hash_value,
+        )?;
+        let expected = params
+            .hash
+            .digest(&verifier_input[..params.salt.len().min(verifier_input.len())]);
+        if verifier_hash.get(..expected.len()) != Some(expected.as_slice()) {
+            return Err(OoxmlError::InvalidPassword);
+        }
+        let key = decrypt_with(&KEY_VALUE_BLOCK_KEY, &self.encrypted_key_value)?;
+        let key = &key[..params.key_bytes.min(key.len())];
+
+        self.verify_integrity(key, encrypted_package)?;
+
+        let size = package_size(encrypted_package)?;
+        let mut package = Vec::with_capacity(size);
+        for (index, segment) in encrypted_package[8..].chunks(SEGMENT_LENGTH).enumerate() {
+            let iv = self.key_data.iv(Some(&(index as u32).to_le_bytes()));
+            package.extend(decrypt_cbc(key, &iv, segment)?);
+        }
+        if package.len() < size {
+            return Err(malformed("encrypted package is truncated"));
+        }
+        package.truncate(size);
+        Ok(package)
+    }
+
+    /// Check the HMAC of the `EncryptedPackage` stream, if the file has one.
+    fn verify_integrity(&self, key: &[u8], encrypted_package: &[u8]) -> Result<(), OoxmlError> {
+        let (encrypted_hmac_key, encrypted_hmac_value) =
+            match (&self.encrypted_hmac_key, &self.encrypted_hmac_value) {
+                (Some(key), Some(value)) => (key, value),
+                _ => return Ok(()),
+            };
+        let hash = self.key_data.hash;
+        let hash_size = hash.digest(&[]).len();
+        let iv = self.key_data.iv(Some(&HMAC_KEY_BLOCK_KEY));
+        let hmac_key = decrypt_cbc(key, &iv, encrypted_hmac_key)?;
+        let iv = self.key_data.iv(Some(&HMAC_VALUE_BLOCK_KEY));
+        let hmac_value = decrypt_cbc(key, &iv, encrypted_hmac_value)?;
+
+        let hmac_key = &hmac_key[..hash_size.min(hmac_key.len())];
+        if hmac_value.get(..hash_size) != Some(hmac(hash, hmac_key, encrypted_package).as_slice()) {
+            return Err(malformed("data integrity check failed"));
+        }
+        Ok(())
+    }
+}
+
+/// Standard encryption (MS-OFFCRYPTO 2.3.4.5), AES-ECB with a SHA-1 derived key, used by
+/// Office 2007.
+struct StandardEncryption {
+    key_bytes: usize,
+    salt: Vec<u8>,
+    encrypted_verifier: Vec<u8>,
+    verifier_hash_size: usize,
+    encrypted_verifier_hash: Vec<u8>,
+}
+
+impl StandardEncryption {
+    fn parse(info: &[u8]) -> Result<Self, OoxmlError> {
+        let flags = read_u32(info, 4)?;
+        if flags & STANDARD_FLAG_CRYPTO_API == 0 || flags & STANDARD_FLAG_AES == 0 {
+            return Err(unsupported(format!(
+                "standard encryption flags {:#x}",
+                flags
+            )));
+        }
+        let header_size = read_u32(info, 8)? as usize;
+        let header = 12;
+        let key_bits = read_u32(info, header + 16)? as usize;
+        let verifier = header + header_size;
+        let salt_size = read_u32(info, verifier)? as usize;
+        let salt = slice(info, verifier + 4, salt_size)?;
+        let encrypted_verifier = slice(info, verifier + 4 + salt_size, 16)?;
+        let verifier_hash_size = read_u32(info, verifier + 20 + salt_size)? as usize;
+        if verifier_hash_size != STANDARD_VERIFIER_HASH_SIZE {
+            return Err(malformed("verifier hash size is not the SHA-1 hash size"));
+        }
+        let encrypted_verifier_hash = slice(info, verifier + 24 + salt_size, 32)?;
+        Ok(Self {
+            key_bytes: key_bits / 8,
+            salt: salt.to_vec(),
+            encrypted_verifier: encrypted_verifier.to_vec(),
+            verifier_hash_size,
+            encrypted_verifier_hash: encrypted_verifier_hash.to_vec(),
+        })
+    }
+
+    /// Derive the key from the password (MS-OFFCRYPTO 2.3.4.7).
+    fn key(&self, password: &str) -> Vec<u8> {
+        let hash = DigestAlgorithm::Sha1;
+        let password_hash = password_hash(hash, &self.salt, password, STANDARD_SPIN_COUNT);
+        let derived = hash.digest(&[password_hash.as_slice(), &0u32.to_le_bytes()].concat());
+        let mut buffer1 = [0x36u8; 64];
+        let mut buffer2 = [0x5cu8; 64];
+        for (i, byte) in derived.iter().enumerate() {
+            buffer1[i] ^= byte;
+            buffer2[i] ^= byte;
+        }
+        let mut key = hash.digest(&buffer1);
+        key.extend(hash.digest(&buffer2));
+        key.truncate(self.key_bytes);
+        key
+    }
+
+    fn decrypt(&self, password: &str, encrypted_package: &[u8]) -> Result<Vec<u8>, OoxmlError> {
+        let key = self.key(password);
+        let verifier = decrypt_ecb(&key, &self.encrypted_verifier)?;
+        let verifier_hash = decrypt_ecb(&key, &self.encrypted_verifier_hash)?;
+        let expected = DigestAlgorithm::Sha1.digest(&verifier);
+        if verifier_hash.get(..self.verifier_hash_size) != expected.get(..self.verifier_hash_size) {
+            return Err(OoxmlError::InvalidPassword);
+        }
+
+        let size = package_size(encrypted_package)?;
+        let data = &encrypted_package[8..];
+        let data = &data[..data.len() - data.len() % AES_BLOCK_SIZE];
+        let mut package = decrypt_ecb(&key, data)?;
+        if package.len() < size {
+            return Err(malformed("encrypted package is truncated"));
+        }
+        package.truncate(size);
+        Ok(package)
+    }
+}
+
+/// Hash the password with `salt` and `spin_count` iterations (MS-OFFCRYPTO 2.3.4.11).
+fn password_hash(hash: DigestAlgorithm, salt: &[u8], password: &str, spin_count: u32) -> Vec<u8> {
+    let password: Vec<u8> = password
+        .encode_utf16()
+        .flat_map(|unit| unit.to_le_bytes())
+        .collect();
+    let mut value = hash.digest(&[salt, password.as_slice()].concat());
+    for i in 0..spin_count {
+        value = hash.digest(&[i.to_le_bytes().as_slice(), value.as_slice()].concat());
+    }
+    value
+}
+
+fn hmac(hash: DigestAlgorithm, key: &[u8], data: &[u8]) -> Vec<u8> {
+    fn compute<M: Mac + KeyInit>(key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut mac = <M as KeyInit>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(data);
+        mac.finalize().into_bytes().to_vec()
+    }
+    match hash {
+        DigestAlgorithm::Sha1 => compute::<Hmac<Sha1>>(key, data),
+        DigestAlgorithm::Sha256 => compute::<Hmac<Sha256>>(key, data),
+        DigestAlgorithm::Sha384 => compute::<Hmac<Sha384>>(key, data),
+        DigestAlgorithm::Sha512 => compute::<Hmac<Sha512>>(key, data),
+    }
+}
+
+fn hash_algorithm(name: &str) -> Option<DigestAlgorithm> {
+    match name {
+        "SHA1" | "SHA-1" => Some(DigestAlgorithm::Sha1),
+        "SHA256" => Some(DigestAlgorithm::Sha256),
+        "SHA384" => Some(DigestAlgorithm::Sha384),
+        "SHA512" => Some(DigestAlgorithm::Sha512),
+        _ => None,
+    }
+}
+
+fn hash_name(hash: DigestAlgorithm) -> &'static str {
+    match hash {
+        DigestAlgorithm::Sha1 => "SHA1",
+        DigestAlgorithm::Sha256 => "SHA256",
+        DigestAlgorithm::Sha384 => "SHA384",
+        DigestAlgorithm::Sha512 => "SHA512",
+    }
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; len];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+/// AES with a key size picked from the key length.
+enum Aes {
+    Aes128(aes::Aes128),
+    Aes192(aes::Aes192),
+    Aes256(aes::Aes256),
+}
+
+impl Aes {
+    fn new(key: &[u8]) -> Result<Self, OoxmlError> {
+        match key.len() {
+            16 => Ok(Aes::Aes128(aes::Aes128::new(key.into()))),
+            24 => Ok(Aes::Aes192(aes::Aes192::new(key.into()))),
+            32 => Ok(Aes::Aes256(aes::Aes256::new(key.into()))),
+            len => Err(unsupported(format!("AES key of {} bits", len * 8))),
+        }
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let block = block.into();
+        match self {
+            Aes::Aes128(aes) => aes.decrypt_block(block),
+            Aes::Aes192(aes) => aes.decrypt_block(block),
+            Aes::Aes256(aes) => aes.decrypt_block(block),
+        }
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let block = block.into();
+        match self {
+            Aes::Aes128(aes) => aes.encrypt_block(block),
+            Aes::Aes192(aes) => aes.encrypt_block(block),
+            Aes::Aes256(aes) => aes.encrypt_block(block),
+        }
+    }
+}
+
+/// Encrypt `data` padded with zeros to the block size.
+fn encrypt_cbc(key: &[u8], iv: &[u8], data: &[u8]) -> Result<Vec<u8>, OoxmlError> {
+    let aes = Aes::new(key)?;
+    let mut output = data.to_vec();
+    output.resize(data.len().div_ceil(AES_BLOCK_SIZE) * AES_BLOCK_SIZE, 0);
+    let mut previous = iv.to_vec();
+    for block in output.chunks_mut(AES_BLOCK_SIZE) {
+        block
+            .iter_mut()
+            .zip(&previous)
+            .for_each(|(byte, iv)| *byte ^= iv);
+        aes.encrypt_block(block);
+        previous = block.to_vec();
+    }
+    Ok(output)
+}
+
+fn decrypt_cbc(key: &[u8], iv: &[u8], data: &[u8]) -> Result<Vec<u8>, OoxmlError> {
+    if !data.len().is_multiple_of(AES_BLOCK_SIZE) || iv.len() != AES_BLOCK_SIZE {
+        return Err(malformed(
+            "encrypted data is not a multiple of the block size",
+        ));
+    }
+    let aes = Aes::new(key)?;
+    let mut previous = iv.to_vec();
+    let mut output = data.to_vec();
+    for block in output.chunks_mut(AES_BLOCK_SIZE) {
+        let ciphertext = block.to_vec();
+        aes.decrypt_block(block);
+        block
+            .iter_mut()
+            .zip(&previous)
+            .for_each(|(byte, iv)| *byte ^= iv);
+        previous = ciphertext;
+    }
+    Ok(output)
+}
+
+fn decrypt_ecb(key: &[u8], data: &[u8]) -> Result<Vec<u8>, OoxmlError> {
+    if !data.len().is_multiple_of(AES_BLOCK_SIZE) {
+        return Err(malformed(
+            "encrypted data is not a multiple of the block size",
+        ));
+    }
+    let aes = Aes::new(key)?;
+    let mut output = data.to_vec();
+    output
+        .chunks_mut(AES_BLOCK_SIZE)
+        .for_each(|block| aes.decrypt_block(block));
+    Ok(output)
+}
+
+/// The size of the decrypted package, checked against the size of the encrypted data so a
+/// forged size never drives an allocation.
+fn package_size(encrypted_package: &[u8]) -> Result<usize, OoxmlError> {
+    let size = read_u64(encrypted_package, 0)?;
+    if size > (encrypted_package.len() - 8) as u64 {
+        return Err(malformed("package size exceeds the encrypted data"));
+    }
+    Ok(size as usize)
+}
+
+fn slice(data: &[u8], offset: usize, len: usize) -> Result<&[u8], OoxmlError> {
+    data.get(offset..offset + len)
+        .ok_or_else(|| malformed("encryption info is truncated"))
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, OoxmlError> {
+    let bytes = slice(data, offset, 2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, OoxmlError> {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(slice(data, offset, 4)?);
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, OoxmlError> {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(slice(data, offset, 8)?);
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn required<'a>(element: &'a XmlElement, name: &str) -> Result<&'a str, OoxmlError> {
+    element
+        .attribute(name)
+        .ok_or_else(|| malformed(&format!("{} is missing on {}", name, element.name)))
+}
+
+fn parse_number<T: std::str::FromStr>(element: &XmlElement, name: &str) -> Result<T, OoxmlError> {
+    required(element, name)?
+        .parse()
+        .map_err(|_| malformed(&format!("{} is not a number", name)))
+}
+
+fn decode_base64(value: &str) -> Result<Vec<u8>, OoxmlError> {
+    BASE64
+        .decode(value)
+        .map_err(|e| malformed(&format!("invalid base64 value: {}", e)))
+}
+
+fn malformed(reason: &str) -> OoxmlError {
+    OoxmlError::EncryptionError(reason.to_string())
+}
+
+fn unsupported(what: String) -> OoxmlError {
+    OoxmlError::EncryptionError(format!("unsupported {}", what))
+}
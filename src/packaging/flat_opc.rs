@@ -0,0 +1,182 @@
+//! Flat OPC, the single XML document representation of a package.
+//!
+//! Every part, relationship parts included, is a `pkg:part` of the `pkg:package` document
+//! element. XML parts are embedded in `pkg:xmlData`, other parts are base64 encoded in
+//! `pkg:binaryData`. There is no content types part, each part carries its content type.
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+use crate::error::OoxmlError;
+use crate::packaging::uri::PackUri;
+use crate::packaging::xml::{NamespaceScope, XmlElement, XmlNode};
+
+pub const FLAT_OPC_NAMESPACE: &str = "http://schemas.microsoft.com/office/2006/xmlPackage";
+
+const PACKAGE_TAG_NAME: &str = "package";
+const PART_TAG_NAME: &str = "part";
+const XML_DATA_TAG_NAME: &str = "xmlData";
+const BINARY_DATA_TAG_NAME: &str = "binaryData";
+const NAME_ATTRIBUTE_NAME: &str = "name";
+const CONTENT_TYPE_ATTRIBUTE_NAME: &str = "contentType";
+const COMPRESSION_ATTRIBUTE_NAME: &str = "compression";
+const PREFIX: &str = "pkg";
+/// Line length of base64 encoded binary data, as written by Office.
+const BINARY_LINE_LENGTH: usize = 76;
+
+/// A part of a flat package.
+#[derive(Debug, Clone)]
+pub(crate) struct FlatPart {
+    pub name: PackUri,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Parse the parts of a flat package.
+pub(crate) fn parse(xml: &[u8]) -> Result<Vec<FlatPart>, OoxmlError> {
+    let root = XmlElement::parse(xml)?;
+    let scope = root.scope(&NamespaceScope::new());
+    if root.local_name() != PACKAGE_TAG_NAME
+        || scope.get(root.prefix()).map(String::as_str) != Some(FLAT_OPC_NAMESPACE)
+    {
+        return Err(invalid(format!(
+            "document element `{}` is not a flat package",
+            root.name
+        )));
+    }
+    let qualified = |local_name: &str| match root.prefix() {
+        "" => local_name.to_string(),
+        prefix => format!("{}:{}", prefix, local_name),
+    };
+
+    let mut parts = Vec::new();
+    for element in root.children_named(PART_TAG_NAME) {
+        let name = element
+            .attribute(&qualified(NAME_ATTRIBUTE_NAME))
+            .ok_or_else(|| invalid("part without a name".to_string()))?;
+        let content_type = element
+            .attribute(&qualified(CONTENT_TYPE_ATTRIBUTE_NAME))
+            .ok_or_else(|| invalid(format!("part {} without a content type", name)))?;
+
+        let data = if let Some(xml_data) = element.child(XML_DATA_TAG_NAME) {
+            let mut content = xml_data
+                .elements()
+                .next()
+                .cloned()
+                .ok_or_else(|| invalid(format!("xml data of part {} is empty", name)))?;
+            // Declarations of the flat document the embedded element relies on.
+            let part_scope = xml_data.scope(&element.scope(&scope));
+            for (prefix, uri) in part_scope {
+                if uri == FLAT_OPC_NAMESPACE {
+                    continue;
+                }
+                let key = match prefix.as_str() {
+                    "" => "xmlns".to_string(),
+                    prefix => format!("xmlns:{}", prefix),
+                };
+                if content.attribute(&key).is_none() {
+                    content.attributes.push((key, uri));
+                }
+            }
+            content.to_xml().into_bytes()
+        } else if let Some(binary_data) = element.child(BINARY_DATA_TAG_NAME) {
+            let encoded: String = binary_data
+                .text()
+                .chars()
+                .filter(|c| !c.is_ascii_whitespace())
+                .collect();
+            BASE64
+                .decode(encoded)
+                .map_err(|e| invalid(format!("binary data of part {}: {}", name, e)))?
+        } else {
+            Vec::new()
+        };
+
+        parts.push(FlatPart {
+            name: PackUri::new(name),
+            content_type: content_type.to_string(),
+            data,
+        });
+    }
+    Ok(parts)
+}
+
+/// Serialize `parts` as a flat package document.
+pub(crate) fn write<'a, I>(parts: I) -> String
+where
+    I: IntoIterator<Item = (&'a PackUri, &'a str, &'a [u8])>,
+{
+    let qualified = |local_name: &str| format!("{}:{}", PREFIX, local_name);
+    let mut package = XmlElement::new(qualified(PACKAGE_TAG_NAME));
+    package.set_attribute(format!("xmlns:{}", PREFIX), FLAT_OPC_NAMESPACE);
+
+    for (name, content_type, data) in parts {
+        let part = package.push_child(XmlElement::new(qualified(PART_TAG_NAME)));
+        part.set_attribute(qualified(NAME_ATTRIBUTE_NAME), name.as_str());
+        part.set_attribute(qualified(CONTENT_TYPE_ATTRIBUTE_NAME), content_type);
+
+        let xml = Some(data)
+            .filter(|_| is_xml_content_type(content_type))
+            .and_then(|data| XmlElement::parse(data).ok());
+        match xml {
+            Some(xml) => {
+                let xml_data = part.push_child(XmlElement::new(qualified(XML_DATA_TAG_NAME)));
+                xml_data.children.push(XmlNode::Element(xml));
+            }
+            None => {
+                part.set_attribute(qualified(COMPRESSION_ATTRIBUTE_NAME), "store");
+                let encoded = BASE64.encode(data);
+                let lines: Vec<&str> = encoded
+                    .as_bytes()
+                    .chunks(BINARY_LINE_LENGTH)
+                    .map(|line| std::str::from_utf8(line).expect("base64 is ascii"))
+                    .collect();
+                part.push_child(XmlElement::new(qualified(BINARY_DATA_TAG_NAME)))
+                    .push_text(lines.join("\n"));
+            }
+        }
+    }
+    package.to_xml()
+}
+
+fn is_xml_content_type(content_type: &str) -> bool {
+    let content_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    content_type.ends_with("+xml")
+        || content_type == "application/xml"
+        || content_type == "text/xml"
+}
+
+fn invalid(reason: String) -> OoxmlError {
+    OoxmlError::XmlError(quick_xml::Error::UnexpectedToken(format!(
+        "invalid flat package: {}",
+        reason
+    )))
+}
+
+#[test]
+fn flat_parts() {
+    let name = PackUri::new("/docProps/thumbnail.png");
+    let image = (0..=255).collect::<Vec<u8>>();
+    let rels = PackUri::new("/_rels/.rels");
+    let xml = write(vec![
+        (
+            &rels,
+            "application/vnd.openxmlformats-package.relationships+xml",
+            br#"<?xml version="1.0"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"/>"#.as_ref(),
+        ),
+        (&name, "image/png", image.as_slice()),
+    ]);
+    assert!(xml.contains("<pkg:xmlData><Relationships "), "{}", xml);
+
+    let parts = parse(xml.as_bytes()).unwrap();
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].name.as_str(), "/_rels/.rels");
+    let relationships = XmlElement::parse(&parts[0].data).unwrap();
+    assert_eq!(relationships.name, "Relationships");
+    assert_eq!(parts[1].content_type, "image/png");
+    assert_eq!(parts[1].data, image);
+}
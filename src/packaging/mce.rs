@@ -0,0 +1,263 @@
+//! Markup Compatibility and Extensibility (ECMA-376 Part 3) preprocessing.
+//!
+//! Typed parts only model the namespaces they understand: the namespace of their document
+//! element, as declared in the part, and the few namespaces the type lists in
+//! [`OpenXmlDeserializeDefault::UNDERSTOOD_NAMESPACES`](super::element::OpenXmlDeserializeDefault::UNDERSTOOD_NAMESPACES).
+//! Before such a part is deserialized, `mc:AlternateContent` is replaced by the first
+//! `mc:Choice` whose required namespaces are understood, or by `mc:Fallback`, and elements
+//! and attributes of ignorable namespaces that are not understood are removed.
+
+use std::collections::HashSet;
+
+use crate::error::OoxmlError;
+use crate::packaging::namespace::transitional_namespace;
+use crate::packaging::xml::{self, NamespaceScope, XmlElement, XmlNode, XML_NAMESPACE_URI};
+
+pub const MARKUP_COMPATIBILITY_NAMESPACE: &str =
+    "http://schemas.openxmlformats.org/markup-compatibility/2006";
+
+const ALTERNATE_CONTENT: &str = "AlternateContent";
+const CHOICE: &str = "Choice";
+const FALLBACK: &str = "Fallback";
+const REQUIRES_ATTRIBUTE: &str = "Requires";
+const IGNORABLE_ATTRIBUTE: &str = "Ignorable";
+const PROCESS_CONTENT_ATTRIBUTE: &str = "ProcessContent";
+const MUST_UNDERSTAND_ATTRIBUTE: &str = "MustUnderstand";
+
+/// Preprocess `data` for a consumer that understands the namespace of the document element
+/// and the `understood` namespaces.
+///
+/// Data without the markup compatibility namespace is returned unchanged.
+pub(crate) fn preprocess(data: &[u8], understood: &[&str]) -> Result<Vec<u8>, OoxmlError> {
+    let namespace = MARKUP_COMPATIBILITY_NAMESPACE.as_bytes();
+    if !data
+        .windows(namespace.len())
+        .any(|window| window == namespace)
+    {
+        return Ok(data.to_vec());
+    }
+
+    let root = XmlElement::parse(data)?;
+    let root_scope = root.scope(&NamespaceScope::new());
+    let root_namespace = resolve(&root_scope, root.prefix()).unwrap_or_default();
+    let mut processor = Processor {
+        understood: understood
+            .iter()
+            .copied()
+            .chain([
+                root_namespace,
+                MARKUP_COMPATIBILITY_NAMESPACE,
+                XML_NAMESPACE_URI,
+            ])
+            .collect(),
+    };
+    let context = Context::default();
+    let mut nodes = processor.element(root, &context)?;
+    match nodes.pop() {
+        Some(XmlNode::Element(root)) if nodes.is_empty() => Ok(root.to_xml().into_bytes()),
+        _ => Err(OoxmlError::XmlError(quick_xml::Error::UnexpectedToken(
+            "document element removed by markup compatibility processing".into(),
+        ))),
+    }
+}
+
+/// Ignorable namespaces and namespaces whose content is processed, inherited by
+/// descendants.
+#[derive(Debug, Clone, Default)]
+struct Context {
+    scope: NamespaceScope,
+    ignorable: HashSet<String>,
+    /// `(namespace, local name)` pairs, `*` matches every local name.
+    process_content: HashSet<(String, String)>,
+}
+
+struct Processor<'a> {
+    understood: HashSet<&'a str>,
+}
+
+impl Processor<'_> {
+    fn element(
+        &mut self,
+        mut element: XmlElement,
+        parent: &Context,
+    ) -> Result<Vec<XmlNode>, OoxmlError> {
+        let mut context = parent.clone();
+        context.scope = element.scope(&parent.scope);
+
+        for (name, value) in &element.attributes {
+            if resolve(&context.scope, xml::prefix(name)) != Some(MARKUP_COMPATIBILITY_NAMESPACE) {
+                continue;
+            }
+            match xml::local_name(name) {
+                IGNORABLE_ATTRIBUTE => {
+                    for prefix in value.split_whitespace() {
+                        let namespace = self.namespace(&context.scope, prefix)?;
+                        if !self.understands(&namespace) {
+                            context.ignorable.insert(namespace);
+                        }
+                    }
+                }
+                PROCESS_CONTENT_ATTRIBUTE => {
+                    for name in value.split_whitespace() {
+                        let namespace = self.namespace(&context.scope, xml::prefix(name))?;
+                        let local_name = xml::local_name(name).to_string();
+                        context.process_content.insert((namespace, local_name));
+                    }
+                }
+                MUST_UNDERSTAND_ATTRIBUTE => {
+                    for prefix in value.split_whitespace() {
+                        let namespace = self.namespace(&context.scope, prefix)?;
+                        if !self.understands(&namespace) {
+                            return Err(OoxmlError::UnsupportedNamespace {
+                                part: String::new(),
+                                namespace,
+                            });
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let namespace = resolve(&context.scope, element.prefix()).unwrap_or_default();
+        if namespace == MARKUP_COMPATIBILITY_NAMESPACE && element.local_name() == ALTERNATE_CONTENT
+        {
+            return match self.select_choice(element, &context)? {
+                Some(choice) => {
+                    let mut choice_context = context.clone();
+                    choice_context.scope = choice.scope(&context.scope);
+                    self.children(choice.children, &choice_context)
+                }
+                None => Ok(Vec::new()),
+            };
+        }
+        if context.ignorable.contains(namespace) {
+            let process_content = context.process_content.iter().any(|(ns, local)| {
+                ns == namespace && (local == "*" || local == element.local_name())
+            });
+            return if process_content {
+                self.children(element.children, &context)
+            } else {
+                Ok(Vec::new())
+            };
+        }
+
+        element.attributes.retain(|(name, _)| {
+            let prefix = xml::prefix(name);
+            if xml::namespace_declaration(name).is_some() || prefix.is_empty() {
+                return true;
+            }
+            match resolve(&context.scope, prefix) {
+                Some(MARKUP_COMPATIBILITY_NAMESPACE) => false,
+                Some(namespace) => !context.ignorable.contains(namespace),
+                None => true,
+            }
+        });
+        let children = std::mem::take(&mut element.children);
+        element.children = self.children(children, &context)?;
+        Ok(vec![XmlNode::Element(element)])
+    }
+
+    fn children(
+        &mut self,
+        children: Vec<XmlNode>,
+        context: &Context,
+    ) -> Result<Vec<XmlNode>, OoxmlError> {
+        let mut nodes = Vec::with_capacity(children.len());
+        for child in children {
+            match child {
+                XmlNode::Element(element) => nodes.extend(self.element(element, context)?),
+                node => nodes.push(node),
+            }
+        }
+        Ok(nodes)
+    }
+
+    /// The first `mc:Choice` whose required namespaces are all understood, or the
+    /// `mc:Fallback`.
+    fn select_choice(
+        &self,
+        alternate_content: XmlElement,
+        context: &Context,
+    ) -> Result<Option<XmlElement>, OoxmlError> {
+        let mut fallback = None;
+        for child in alternate_content.children {
+            let child = match child {
+                XmlNode::Element(child) => child,
+                _ => continue,
+            };
+            let scope = child.scope(&context.scope);
+            if resolve(&scope, child.prefix()) != Some(MARKUP_COMPATIBILITY_NAMESPACE) {
+                continue;
+            }
+            match child.local_name() {
+                CHOICE => {
+                    let requires = child.attribute(REQUIRES_ATTRIBUTE).unwrap_or_default();
+                    let mut understood = true;
+                    for prefix in requires.split_whitespace() {
+                        let namespace = self.namespace(&scope, prefix)?;
+                        understood &= self.understands(&namespace);
+                    }
+                    if understood {
+                        return Ok(Some(child));
+                    }
+                }
+                FALLBACK if fallback.is_none() => fallback = Some(child),
+                _ => {}
+            }
+        }
+        Ok(fallback)
+    }
+
+    /// Strict namespaces are understood when their Transitional equivalent is.
+    fn understands(&self, namespace: &str) -> bool {
+        self.understood.contains(namespace)
+            || self
+                .understood
+                .contains(transitional_namespace(namespace).as_ref())
+    }
+
+    fn namespace(&self, scope: &NamespaceScope, prefix: &str) -> Result<String, OoxmlError> {
+        resolve(scope, prefix).map(String::from).ok_or_else(|| {
+            OoxmlError::XmlError(quick_xml::Error::UnexpectedToken(format!(
+                "undeclared namespace prefix `{}`",
+                prefix
+            )))
+        })
+    }
+}
+
+fn resolve<'a>(scope: &'a NamespaceScope, prefix: &str) -> Option<&'a str> {
+    if prefix == "xml" {
+        return Some(XML_NAMESPACE_URI);
+    }
+    scope.get(prefix).map(String::as_str)
+}
+
+#[test]
+fn alternate_content_and_ignorable() {
+    let xml = r#"<worksheet xmlns="urn:main" xmlns:mc="http://schemas.openxmlformats.org/markup-compatibility/2006" xmlns:x14ac="urn:x14ac" xmlns:x14="urn:x14" mc:Ignorable="x14ac" x14ac:dyDescent="0.25"><mc:AlternateContent><mc:Choice Requires="x14"><x14:sheetPr/></mc:Choice><mc:Fallback><sheetPr code="1"/></mc:Fallback></mc:AlternateContent><x14ac:extra><cell/></x14ac:extra><row x14ac:spans="1:2"/></worksheet>"#;
+    let processed = preprocess(xml.as_bytes(), &[]).unwrap();
+    let processed = XmlElement::parse(&processed).unwrap();
+    let names: Vec<&str> = processed.elements().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["sheetPr", "row"]);
+    assert_eq!(processed.attribute("x14ac:dyDescent"), None);
+    assert_eq!(processed.attribute("mc:Ignorable"), None);
+    assert_eq!(processed.child("row").unwrap().attributes.len(), 0);
+
+    // The understood choice wins over the fallback.
+    let processed = preprocess(xml.as_bytes(), &["urn:x14"]).unwrap();
+    let processed = XmlElement::parse(&processed).unwrap();
+    assert!(processed.child("sheetPr").unwrap().name == "x14:sheetPr");
+
+    let xml = r#"<a xmlns:mc="http://schemas.openxmlformats.org/markup-compatibility/2006" xmlns:v="urn:v" mc:Ignorable="v" mc:ProcessContent="v:wrap"><v:wrap><b/></v:wrap></a>"#;
+    let processed = preprocess(xml.as_bytes(), &[]).unwrap();
+    let processed = XmlElement::parse(&processed).unwrap();
+    assert!(processed.child("b").is_some());
+
+    let xml = r#"<a xmlns:mc="http://schemas.openxmlformats.org/markup-compatibility/2006" xmlns:v="urn:v" mc:MustUnderstand="v"/>"#;
+    assert!(matches!(
+        preprocess(xml.as_bytes(), &[]),
+        Err(OoxmlError::UnsupportedNamespace { namespace, .. }) if namespace == "urn:v"
+    ));
+}
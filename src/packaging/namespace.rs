@@ -1,9 +1,82 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+
 use linked_hash_map::LinkedHashMap;
 use serde::de::MapAccess;
 use serde::de::Visitor;
 use serde::ser::SerializeMap;
 use serde::{Deserialize, Serialize};
 
+/// Base of the ISO/IEC 29500 Strict namespaces and relationship types.
+pub const STRICT_NAMESPACE_BASE: &str = "http://purl.oclc.org/ooxml/";
+/// Base of the Transitional namespaces and relationship types.
+pub const TRANSITIONAL_NAMESPACE_BASE: &str = "http://schemas.openxmlformats.org/";
+
+/// Conformance class of a document, i.e. which family of namespaces it is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Conformance {
+    #[default]
+    Transitional,
+    Strict,
+}
+
+impl Conformance {
+    /// Conformance class of a namespace or relationship type URI.
+    pub fn of(uri: &str) -> Self {
+        if uri.starts_with(STRICT_NAMESPACE_BASE) {
+            Conformance::Strict
+        } else {
+            Conformance::Transitional
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Conformance::Transitional => "transitional",
+            Conformance::Strict => "strict",
+        }
+    }
+}
+
+impl Display for Conformance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Map a Strict namespace or relationship type URI to its Transitional equivalent, other
+/// URIs are returned unchanged.
+///
+/// `http://purl.oclc.org/ooxml/spreadsheetml/main` becomes
+/// `http://schemas.openxmlformats.org/spreadsheetml/2006/main`, the properties names that
+/// Strict spells in camel case are spelled with hyphens.
+pub fn transitional_namespace(uri: &str) -> Cow<'_, str> {
+    let (area, name) = match uri
+        .strip_prefix(STRICT_NAMESPACE_BASE)
+        .and_then(|path| path.split_once('/'))
+    {
+        Some(path) => path,
+        None => return Cow::Borrowed(uri),
+    };
+    let name = match name {
+        "extendedProperties" => "extended-properties",
+        "customProperties" => "custom-properties",
+        "relationships/extendedProperties" => "relationships/extended-properties",
+        "relationships/customProperties" => "relationships/custom-properties",
+        name => name,
+    };
+    Cow::Owned(format!(
+        "{}{}/2006/{}",
+        TRANSITIONAL_NAMESPACE_BASE, area, name
+    ))
+}
+
+/// Whether two namespace or relationship type URIs are equal once Strict URIs are mapped
+/// to Transitional ones.
+pub fn namespace_eq(a: &str, b: &str) -> bool {
+    a == b || transitional_namespace(a) == transitional_namespace(b)
+}
+
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct Namespaces(LinkedHashMap<String, String>);
 
@@ -11,6 +84,20 @@ impl Namespaces {
     pub fn add_namespace<S1: Into<String>, S2: Into<String>>(&mut self, decl: S1, uri: S2) {
         self.0.insert(decl.into(), uri.into());
     }
+
+    /// The namespace declared by `decl`, e.g. `xmlns` or `xmlns:r`.
+    pub fn get(&self, decl: &str) -> Option<&str> {
+        self.0.get(decl).map(String::as_str)
+    }
+
+    /// Strict when any declared namespace is a Strict one.
+    pub fn conformance(&self) -> Conformance {
+        self.0
+            .values()
+            .map(|uri| Conformance::of(uri))
+            .find(|conformance| *conformance == Conformance::Strict)
+            .unwrap_or_default()
+    }
 }
 
 impl<'de> Deserialize<'de> for Namespaces {
@@ -65,3 +152,29 @@ impl<'de> Visitor<'de> for NamespaceVisitor {
         Ok(ns)
     }
 }
+
+#[test]
+fn strict_namespaces() {
+    assert_eq!(
+        transitional_namespace("http://purl.oclc.org/ooxml/spreadsheetml/main"),
+        "http://schemas.openxmlformats.org/spreadsheetml/2006/main"
+    );
+    assert_eq!(
+        transitional_namespace(
+            "http://purl.oclc.org/ooxml/officeDocument/relationships/extendedProperties"
+        ),
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties"
+    );
+    assert_eq!(
+        transitional_namespace("http://purl.org/dc/terms/"),
+        "http://purl.org/dc/terms/"
+    );
+    assert!(namespace_eq(
+        "http://purl.oclc.org/ooxml/officeDocument/relationships/officeDocument",
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument"
+    ));
+    assert_eq!(
+        Conformance::of("http://purl.oclc.org/ooxml/spreadsheetml/main"),
+        Conformance::Strict
+    );
+}
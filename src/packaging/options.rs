@@ -0,0 +1,62 @@
+/// Options used when opening an [`OpenXmlPackage`](super::package::OpenXmlPackage).
+///
+/// The limits guard against packages crafted to exhaust memory, e.g. zip bombs. They are
+/// unset by default, [`OpenOptions::untrusted`] sets limits suited to uploaded files.
+#[derive(Debug, Clone, Default)]
+pub struct OpenOptions {
+    /// Keep the zip archive open and only decompress a part when its data is first read.
+    ///
+    /// `[Content_Types].xml`, `_rels/.rels` and the docProps parts are always read when
+    /// opening.
+    pub lazy: bool,
+    /// Maximum decompressed size of a single part in bytes.
+    pub max_part_size: Option<u64>,
+    /// Maximum decompressed size of all parts in bytes.
+    ///
+    /// In lazy mode the sizes recorded in the zip directory are checked when opening.
+    pub max_total_size: Option<u64>,
+    /// Maximum number of zip entries, including relationship parts.
+    pub max_part_count: Option<usize>,
+    /// Maximum ratio of the decompressed to the compressed size of a part.
+    pub max_compression_ratio: Option<u64>,
+    /// Maximum nesting depth of elements in XML parts.
+    pub max_xml_depth: Option<usize>,
+}
+
+impl OpenOptions {
+    pub fn lazy() -> Self {
+        Self {
+            lazy: true,
+            ..Default::default()
+        }
+    }
+
+    /// Limits for packages from untrusted sources: 256 MiB per part, 1 GiB in total,
+    /// 10000 parts, a compression ratio of 250 and an XML depth of 256.
+    pub fn untrusted() -> Self {
+        Self {
+            max_part_size: Some(256 << 20),
+            max_total_size: Some(1 << 30),
+            max_part_count: Some(10_000),
+            max_compression_ratio: Some(250),
+            max_xml_depth: Some(256),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn part_limits(&self) -> PartLimits {
+        PartLimits {
+            max_part_size: self.max_part_size,
+            max_compression_ratio: self.max_compression_ratio,
+            max_xml_depth: self.max_xml_depth,
+        }
+    }
+}
+
+/// The limits checked whenever a single part is decompressed.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct PartLimits {
+    pub max_part_size: Option<u64>,
+    pub max_compression_ratio: Option<u64>,
+    pub max_xml_depth: Option<usize>,
+}
@@ -1,23 +1,54 @@
-use std::io::{Read, Seek};
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, Write};
 use std::path::Path;
+use std::sync::{Arc, Mutex};
 
 use crate::error::OoxmlError;
 
 use linked_hash_map::LinkedHashMap;
-use zip::ZipArchive;
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
 
 use crate::packaging::{
-    app_property::AppProperties, content_type::ContentTypes, custom_property::CustomProperties,
-    part::OpenXmlPart, property::Properties, relationship::Relationships,
+    app_property::{
+        AppProperties, APP_PROPERTIES_CONTENT_TYPE, APP_PROPERTIES_URI, APP_PROPERTY_TAGS,
+    },
+    content_type::{content_type_eq, ContentType, ContentTypes, CONTENT_TYPES_TAGS},
+    custom_property::{
+        CustomProperties, CUSTOM_PROPERTIES_CONTENT_TYPE, CUSTOM_PROPERTIES_URI,
+        CUSTOM_PROPERTY_TAG,
+    },
+    namespace::Conformance,
+    options::OpenOptions,
+    part::{read_entry, OpenXmlPart, ReadSeek, SharedArchive},
+    property::{Properties, CORE_PROPERTIES_CONTENT_TYPE, CORE_PROPERTIES_URI, PROPERTY_TAGS},
+    relationship::{Relationship, Relationships, RELATIONSHIPS_TAGS},
+    signature::{PackageSignature, SignOptions, SigningKey},
+    uri::{self, validate_part_name, PackUri},
+    validation::{Diagnostic, DiagnosticKind},
+    variant::Variant,
 };
 
 use crate::packaging::{
-    app_property::APP_PROPERTIES_URI, content_type::CONTENT_TYPES_FILE,
-    custom_property::CUSTOM_PROPERTIES_URI, property::CORE_PROPERTIES_URI,
-    relationship::RELATIONSHIPS_FILE,
+    content_type::CONTENT_TYPES_FILE,
+    relationship::{
+        CORE_PROPERTIES_RELATIONSHIP_TYPE, CUSTOM_PROPERTIES_RELATIONSHIP_TYPE,
+        EXTENDED_PROPERTIES_RELATIONSHIP_TYPE, OFFICE_DOCUMENT_RELATIONSHIP_TYPE,
+        THUMBNAIL_RELATIONSHIP_TYPE,
+    },
+    signature::{
+        DIGITAL_SIGNATURE_CONTENT_TYPE, DIGITAL_SIGNATURE_ORIGIN_CONTENT_TYPE,
+        DIGITAL_SIGNATURE_ORIGIN_RELATIONSHIP_TYPE, DIGITAL_SIGNATURE_RELATIONSHIP_TYPE,
+        RELATIONSHIPS_CONTENT_TYPE,
+    },
 };
 
 use crate::packaging::element::*;
+use crate::packaging::{encryption, flat_opc, signature, xml};
+
+const DIGITAL_SIGNATURE_ORIGIN_PART: &str = "/_xmlsignatures/origin.sigs";
+const THUMBNAIL_PART_STEM: &str = "/docProps/thumbnail";
+/// Content type of parts that have neither an override nor an extension default.
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
 
 #[derive(Debug, Clone, Default)]
 pub struct OpenXmlPackage {
@@ -26,7 +57,11 @@ pub struct OpenXmlPackage {
     app_properties: AppProperties,
     properties: Properties,
     coustom_properties: Option<CustomProperties>,
-    parts: LinkedHashMap<String, OpenXmlPart>,
+    parts: LinkedHashMap<PackUri, OpenXmlPart>,
+    part_relationships: LinkedHashMap<PackUri, Relationships>,
+    duplicate_part_names: Vec<String>,
+    /// `[Content_Types].xml` and the relationship parts as read, by zip item name.
+    xml_sources: HashMap<String, String>,
 }
 
 impl OpenXmlPackage {
@@ -35,62 +70,884 @@ impl OpenXmlPackage {
         Self::from_reader(file)
     }
 
-    fn from_reader<R: Read + Seek>(reader: R) -> Result<Self, OoxmlError> {
-        let mut zip = ZipArchive::new(reader)?;
+    /// Open a package from in-memory bytes, e.g. an uploaded `Vec<u8>`.
+    pub fn from_bytes<B: AsRef<[u8]>>(bytes: B) -> Result<Self, OoxmlError> {
+        Self::from_reader(Cursor::new(bytes))
+    }
+
+    pub fn open_with_options<P: AsRef<Path>>(
+        path: P,
+        options: OpenOptions,
+    ) -> Result<Self, OoxmlError> {
+        let file = std::fs::File::open(path)?;
+        Self::from_reader_with_options(file, options)
+    }
+
+    /// Open a Flat OPC package, the single XML document form of a package that Word and
+    /// PowerPoint save as "XML Document".
+    pub fn from_flat_opc<B: AsRef<[u8]>>(xml: B) -> Result<Self, OoxmlError> {
         let mut package = OpenXmlPackage::default();
-        let mut content_types_id = None;
-        for i in 0..zip.len() {
-            let mut file = zip.by_index(i)?;
-            if file.is_dir() {
+        package
+            .content_types
+            .add_default_element("rels".to_string(), RELATIONSHIPS_CONTENT_TYPE.to_string());
+        let mut has_relationships_part = false;
+        for flat_opc::FlatPart {
+            name,
+            content_type,
+            data,
+        } in flat_opc::parse(xml.as_ref())?
+        {
+            if let Some(source) = name.source_part() {
+                let xml = utf8(data)?;
+                let relationships = Relationships::parse_from_xml_str(&xml)
+                    .map_err(|e| e.in_part(name.as_str()))?;
+                if source.is_root() {
+                    if has_relationships_part {
+                        package.duplicate_part_names.push(name.to_string());
+                    }
+                    has_relationships_part = true;
+                    package.relationships = relationships;
+                } else if package
+                    .part_relationships
+                    .insert(source, relationships)
+                    .is_some()
+                {
+                    package.duplicate_part_names.push(name.to_string());
+                }
                 continue;
             }
+            if package.parts.contains_key(&name) {
+                package.duplicate_part_names.push(name.to_string());
+            }
+            package.insert_part(name, &content_type, data)?;
+        }
+        if !has_relationships_part {
+            let relationships_part = PackUri::root().relationships_part();
+            return Err(OoxmlError::PartNotFound(relationships_part.to_string()));
+        }
+        package.load_properties()?;
+        Ok(package)
+    }
+
+    /// Open a password protected package, see [`OpenXmlPackage::from_reader_with_password`].
+    pub fn open_with_password<P: AsRef<Path>>(path: P, password: &str) -> Result<Self, OoxmlError> {
+        let file = std::fs::File::open(path)?;
+        Self::from_reader_with_password(file, password)
+    }
+
+    /// Open a package encrypted with a password, using Agile or Standard encryption.
+    ///
+    /// Packages that are not encrypted are opened as usual.
+    pub fn from_reader_with_password<R: Read + Seek>(
+        mut reader: R,
+        password: &str,
+    ) -> Result<Self, OoxmlError> {
+        if !encryption::is_encrypted_package(&mut reader)? {
+            return Self::from_reader(reader);
+        }
+        let options = OpenOptions::default();
+        let package = encryption::decrypt(reader, password, options.max_total_size)?;
+        Self::read_eager(Cursor::new(package), &options)
+    }
+
+    /// Open a package encrypted with a password with `options`, whose limits also apply
+    /// to the decrypted package, see [`OpenXmlPackage::from_reader_with_options`].
+    pub fn from_reader_with_password_and_options<R: Read + Seek + Send + 'static>(
+        mut reader: R,
+        password: &str,
+        options: OpenOptions,
+    ) -> Result<Self, OoxmlError> {
+        if !encryption::is_encrypted_package(&mut reader)? {
+            return Self::from_reader_with_options(reader, options);
+        }
+        let package = encryption::decrypt(reader, password, options.max_total_size)?;
+        Self::from_reader_with_options(Cursor::new(package), options)
+    }
+
+    /// Open a package from `reader`.
+    ///
+    /// Fails with [`OoxmlError::PasswordRequired`] if the package is encrypted.
+    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Self, OoxmlError> {
+        Self::read_eager(reader, &OpenOptions::default())
+    }
 
-            println!("file {} is {:?} ", i, file.name().to_string());
+    fn read_eager<R: Read + Seek>(
+        mut reader: R,
+        options: &OpenOptions,
+    ) -> Result<Self, OoxmlError> {
+        if encryption::is_encrypted_package(&mut reader)? {
+            return Err(OoxmlError::PasswordRequired);
+        }
+        let mut zip = ZipArchive::new(reader)?;
+        let mut package = Self::read_archive(&mut zip, None, options)?;
+        package.load_properties()?;
+        Ok(package)
+    }
+
+    /// Open a package with `options`.
+    ///
+    /// In lazy mode the reader is kept by the package, so it must be `Send + 'static`.
+    /// A limit of `options` that is exceeded fails with the matching error, e.g.
+    /// [`OoxmlError::PartTooLarge`].
+    pub fn from_reader_with_options<R: Read + Seek + Send + 'static>(
+        mut reader: R,
+        options: OpenOptions,
+    ) -> Result<Self, OoxmlError> {
+        if !options.lazy {
+            return Self::read_eager(reader, &options);
+        }
+        if encryption::is_encrypted_package(&mut reader)? {
+            return Err(OoxmlError::PasswordRequired);
+        }
+
+        let reader: Box<dyn ReadSeek> = Box::new(reader);
+        let archive: SharedArchive = Arc::new(Mutex::new(ZipArchive::new(reader)?));
+        let mut package = {
+            let mut zip = archive
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            Self::read_archive(&mut zip, Some(&archive), &options)?
+        };
+        package.load_properties()?;
+        Ok(package)
+    }
+
+    fn read_archive<R: Read + Seek>(
+        zip: &mut ZipArchive<R>,
+        lazy: Option<&SharedArchive>,
+        options: &OpenOptions,
+    ) -> Result<Self, OoxmlError> {
+        if let Some(limit) = options.max_part_count.filter(|&limit| zip.len() > limit) {
+            return Err(OoxmlError::TooManyParts {
+                count: zip.len(),
+                limit,
+            });
+        }
+        let limits = options.part_limits();
+        let mut total_size = 0u64;
+
+        let mut package = OpenXmlPackage::default();
+        let mut has_content_types_part = false;
+        let mut has_relationships_part = false;
+        for entry in zip_entries(zip)? {
+            let filename = entry.name;
+            log::debug!("file {:?} is {:?}", entry.indices, filename);
+
+            let part_name = PackUri::new(&filename);
+            let is_xml = filename == CONTENT_TYPES_FILE || part_name.is_relationships_part();
+            let mut size = 0u64;
+            let mut compressed_size = 0u64;
+            let data = match lazy {
+                Some(_) if !is_xml && entry.indices.len() == 1 => {
+                    let file = zip.by_index(entry.indices[0])?;
+                    size = file.size();
+                    compressed_size = file.compressed_size();
+                    total_size = total_size.saturating_add(size);
+                    None
+                }
+                _ => {
+                    // The pieces of a part are concatenated in order.
+                    let mut data = Vec::new();
+                    for &index in &entry.indices {
+                        let mut file = zip.by_index(index)?;
+                        size = size.saturating_add(file.size());
+                        compressed_size = compressed_size.saturating_add(file.compressed_size());
+                        let remaining_total = options.max_total_size.map(|limit| {
+                            limit.saturating_sub(total_size.saturating_add(data.len() as u64))
+                        });
+                        data.extend(read_entry(&mut file, &limits, remaining_total)?);
+                        if let Some(limit) = limits
+                            .max_part_size
+                            .filter(|&limit| data.len() as u64 > limit)
+                        {
+                            return Err(OoxmlError::PartTooLarge {
+                                part: part_name.to_string(),
+                                limit,
+                            });
+                        }
+                    }
+                    total_size = total_size.saturating_add(data.len() as u64);
+                    Some(data)
+                }
+            };
+            if let Some(limit) = options.max_total_size.filter(|&limit| total_size > limit) {
+                return Err(OoxmlError::PackageTooLarge { limit });
+            }
 
-            let filename = file.name().to_string();
             if filename == CONTENT_TYPES_FILE {
-                content_types_id = Some(i);
-                let mut xml = String::new();
-                file.read_to_string(&mut xml)?;
-                package.content_types = ContentTypes::parse_from_xml_str(&xml);
-                continue;
-            } else if filename == RELATIONSHIPS_FILE {
-                let mut xml = String::new();
-                file.read_to_string(&mut xml)?;
-                package.relationships = Relationships::parse_from_xml_str(&xml);
+                has_content_types_part = true;
+                let xml = utf8(data.unwrap_or_default())?;
+                package.content_types = ContentTypes::parse_from_xml_str(&xml)?;
+                package.xml_sources.insert(filename, xml);
                 continue;
-            } else if filename == CORE_PROPERTIES_URI {
-                let mut xml = String::new();
-                file.read_to_string(&mut xml)?;
-                package.properties = Properties::parse_from_xml_str(&xml);
+            }
+
+            if let Some(source) = part_name.source_part() {
+                let xml = utf8(data.unwrap_or_default())?;
+                let relationships = Relationships::parse_from_xml_str(&xml)
+                    .map_err(|e| e.in_part(part_name.as_str()))?;
+                package.xml_sources.insert(filename.clone(), xml);
+                if source.is_root() {
+                    if has_relationships_part {
+                        package.duplicate_part_names.push(filename);
+                    }
+                    has_relationships_part = true;
+                    package.relationships = relationships;
+                } else if package
+                    .part_relationships
+                    .insert(source, relationships)
+                    .is_some()
+                {
+                    package.duplicate_part_names.push(filename);
+                }
                 continue;
-            } else if filename == CUSTOM_PROPERTIES_URI {
-                let mut xml = String::new();
-                file.read_to_string(&mut xml)?;
-                package.coustom_properties = Some(CustomProperties::parse_from_xml_str(&xml));
-            } else if filename == APP_PROPERTIES_URI {
-                let mut xml = String::new();
-                file.read_to_string(&mut xml)?;
-                package.app_properties = OpenXmlDeserialized::from_xml_str(&xml).unwrap();
-            } else {
-                let uri = std::path::PathBuf::from(&filename);
-                let part = OpenXmlPart::from_reader(uri, &mut file)?;
-                package.parts.insert(filename, part);
+            }
+
+            let uri = std::path::PathBuf::from(&filename);
+            let mut part = match (lazy, data) {
+                (_, Some(data)) => OpenXmlPart::from_data(uri, data),
+                (Some(archive), None) => {
+                    OpenXmlPart::lazy(uri, archive.clone(), entry.indices[0], limits)
+                }
+                (None, None) => unreachable!("parts are read unless the package is lazy"),
+            };
+            part.set_zip_sizes(size, compressed_size);
+            if package.parts.insert(part_name, part).is_some() {
+                package.duplicate_part_names.push(filename);
             }
         }
 
-        if content_types_id.is_none() {
+        if !has_content_types_part || !package.has_content_types() {
             return Err(OoxmlError::PackageContentTypeError);
         }
-
-        assert!(package.has_content_types());
-        assert!(package.has_relationships());
+        if !has_relationships_part {
+            let relationships_part = PackUri::root().relationships_part();
+            return Err(OoxmlError::PartNotFound(relationships_part.to_string()));
+        }
+        package.resolve_content_types();
 
         Ok(package)
     }
 
+    /// Set the content type of every part from the overrides and extension defaults.
+    fn resolve_content_types(&mut self) {
+        let overrides: HashMap<PackUri, &ContentType> = self
+            .content_types
+            .overrides()
+            .map(|(part_name, content_type)| (PackUri::new(part_name), content_type))
+            .collect();
+        let content_types = &self.content_types;
+        for (name, part) in self.parts.iter_mut() {
+            let content_type = overrides
+                .get(name)
+                .copied()
+                .or_else(|| content_types.get_default_content_type(name.extension()?));
+            part.set_content_type(content_type.cloned());
+        }
+    }
+
+    /// Parse the docProps parts targeted by the package relationships.
+    fn load_properties(&mut self) -> Result<(), OoxmlError> {
+        if let Some(part) = self.get_related_part(CORE_PROPERTIES_RELATIONSHIP_TYPE) {
+            self.properties = Properties::from_part(part)?;
+        }
+        if let Some(part) = self.get_related_part(CUSTOM_PROPERTIES_RELATIONSHIP_TYPE) {
+            self.coustom_properties = Some(CustomProperties::from_part(part)?);
+        }
+        if let Some(part) = self.get_related_part(EXTENDED_PROPERTIES_RELATIONSHIP_TYPE) {
+            self.app_properties = AppProperties::from_part(part)?;
+        }
+        Ok(())
+    }
+
+    /// Get the first part targeted by a package relationship of `relationship_type`.
+    pub fn get_related_part(&self, relationship_type: &str) -> Option<&OpenXmlPart> {
+        self.related_parts("/", relationship_type)
+            .into_iter()
+            .next()
+    }
+
+    /// Save the package as a zip file at `path`.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), OoxmlError> {
+        let file = std::fs::File::create(path)?;
+        self.write_to(file)
+    }
+
+    /// Serialize the package into zip format.
+    ///
+    /// `[Content_Types].xml` and the relationship parts are generated from the package
+    /// model, every other part is written as it is stored.
+    pub fn write_to<W: Write + Seek>(&self, writer: W) -> Result<(), OoxmlError> {
+        let mut zip = ZipWriter::new(writer);
+        let options = FileOptions::default();
+
+        zip.start_file(CONTENT_TYPES_FILE, options)?;
+        zip.write_all(&self.content_types_xml()?)?;
+
+        let root = PackUri::root();
+        let relationships = std::iter::once((&root, &self.relationships))
+            .chain(&self.part_relationships)
+            .filter(|(source, relationships)| source.is_root() || !relationships.is_empty());
+        for (source, relationships) in relationships {
+            let part_name = source.relationships_part();
+            zip.start_file(part_name.zip_item_name(), options)?;
+            zip.write_all(&self.relationships_xml(&part_name, relationships)?)?;
+        }
+
+        for (name, part) in &self.parts {
+            zip.start_file(name.zip_item_name(), options)?;
+            zip.write_all(part.data()?)?;
+        }
+
+        zip.finish()?;
+        Ok(())
+    }
+
+    /// Serialize the package as a Flat OPC document.
+    ///
+    /// XML parts are embedded as they are, other parts are base64 encoded.
+    pub fn to_flat_opc(&self) -> Result<String, OoxmlError> {
+        let root = PackUri::root();
+        let mut relationship_parts = Vec::new();
+        let relationships = std::iter::once((&root, &self.relationships))
+            .chain(&self.part_relationships)
+            .filter(|(source, relationships)| source.is_root() || !relationships.is_empty());
+        for (source, relationships) in relationships {
+            let part_name = source.relationships_part();
+            let data = self.relationships_xml(&part_name, relationships)?;
+            relationship_parts.push((part_name, data));
+        }
+
+        let mut parts = Vec::with_capacity(self.parts.len());
+        for (name, part) in &self.parts {
+            let content_type = part
+                .content_type()
+                .or_else(|| self.content_types.get_content_type(name))
+                .map(String::as_str)
+                .unwrap_or(DEFAULT_CONTENT_TYPE);
+            parts.push((name, content_type, part.data()?));
+        }
+
+        let relationship_parts = relationship_parts
+            .iter()
+            .map(|(name, data)| (name, RELATIONSHIPS_CONTENT_TYPE, data.as_slice()));
+        Ok(flat_opc::write(relationship_parts.chain(parts)))
+    }
+
+    /// The `[Content_Types].xml` to write: the part as read while the content types are
+    /// unchanged, else the generated part with the unknown content of the original.
+    fn content_types_xml(&self) -> Result<Vec<u8>, OoxmlError> {
+        let source = self.xml_sources.get(CONTENT_TYPES_FILE);
+        if let Some(source) = source {
+            if ContentTypes::parse_from_xml_str(source).ok().as_ref() == Some(&self.content_types) {
+                return Ok(source.clone().into_bytes());
+            }
+        }
+        let mut generated = Vec::new();
+        self.content_types.write(&mut generated)?;
+        match source {
+            Some(source) => xml::preserve_unknown(source.as_bytes(), generated, CONTENT_TYPES_TAGS),
+            None => Ok(generated),
+        }
+    }
+
+    /// The relationship part `part_name` to write, like [`Self::content_types_xml`].
+    fn relationships_xml(
+        &self,
+        part_name: &PackUri,
+        relationships: &Relationships,
+    ) -> Result<Vec<u8>, OoxmlError> {
+        let source = self.xml_sources.get(part_name.zip_item_name());
+        if let Some(source) = source {
+            if Relationships::parse_from_xml_str(source).ok().as_ref() == Some(relationships) {
+                return Ok(source.clone().into_bytes());
+            }
+        }
+        let mut generated = Vec::new();
+        relationships.write(&mut generated)?;
+        match source {
+            Some(source) => xml::preserve_unknown(source.as_bytes(), generated, RELATIONSHIPS_TAGS),
+            None => Ok(generated),
+        }
+    }
+
+    /// Save the package encrypted with `password`.
+    pub fn save_with_password<P: AsRef<Path>>(
+        &self,
+        path: P,
+        password: &str,
+    ) -> Result<(), OoxmlError> {
+        let mut file = std::fs::File::create(path)?;
+        self.write_to_with_password(&mut file, password)
+    }
+
+    /// Serialize the package and encrypt it with `password`.
+    ///
+    /// The zip package is wrapped in a compound file with Agile encryption (AES-256 and
+    /// SHA-512) and a data integrity HMAC, as written by Office.
+    pub fn write_to_with_password<W: Write>(
+        &self,
+        mut writer: W,
+        password: &str,
+    ) -> Result<(), OoxmlError> {
+        let mut package = std::io::Cursor::new(Vec::new());
+        self.write_to(&mut package)?;
+        writer.write_all(&encryption::encrypt(package.get_ref(), password)?)?;
+        Ok(())
+    }
+
+    /// Check the package against the OPC conformance rules that can be violated by a
+    /// readable package, e.g. parts without a content type or dangling relationships.
+    ///
+    /// Returns an empty list for a conformant package.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        for name in &self.duplicate_part_names {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticKind::DuplicatePartName,
+                Some(&format!("/{}", name)),
+                "zip item name is equivalent to the name of another part",
+            ));
+        }
+
+        for (name, part) in &self.parts {
+            let raw_name = format!("/{}", part.uri().to_string_lossy());
+            if let Err(reason) = validate_part_name(&raw_name) {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::IllegalPartName,
+                    Some(&raw_name),
+                    reason,
+                ));
+            }
+            if part.content_type().is_none() {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::MissingContentType,
+                    Some(name.as_str()),
+                    "part has no content type override and no default for its extension",
+                ));
+            }
+        }
+
+        for (part_name, _) in self.content_types.overrides() {
+            let name = PackUri::new(part_name);
+            let exists = match name.source_part() {
+                Some(source) if source.is_root() => true,
+                Some(source) => self.part_relationships.contains_key(&source),
+                None => self.parts.contains_key(&name),
+            };
+            if !exists {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::OverrideWithoutPart,
+                    Some(part_name),
+                    "content type override names a part that does not exist",
+                ));
+            }
+        }
+
+        let root = PackUri::root();
+        let relationships =
+            std::iter::once((&root, &self.relationships)).chain(&self.part_relationships);
+        for (source, relationships) in relationships {
+            let rels_part = source.relationships_part();
+            for id in relationships.duplicate_ids() {
+                diagnostics.push(Diagnostic::new(
+                    DiagnosticKind::DuplicateRelationshipId,
+                    Some(rels_part.as_str()),
+                    format!("relationship id {} is used more than once", id),
+                ));
+            }
+            for relationship in relationships.iter() {
+                let target = match relationship.target_part(source) {
+                    Some(target) => target,
+                    None => continue,
+                };
+                if !self.parts.contains_key(&target) {
+                    diagnostics.push(Diagnostic::new(
+                        DiagnosticKind::DanglingRelationship,
+                        Some(rels_part.as_str()),
+                        format!(
+                            "relationship {} targets missing part {}",
+                            relationship.id(),
+                            target
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if self
+            .get_related_part(OFFICE_DOCUMENT_RELATIONSHIP_TYPE)
+            .is_none()
+        {
+            diagnostics.push(Diagnostic::new(
+                DiagnosticKind::MissingMainDocument,
+                None,
+                "package has no officeDocument relationship to an existing part",
+            ));
+        }
+
+        diagnostics
+    }
+
+    /// Get the digital signatures of the package, see [`PackageSignature::verify`].
+    pub fn signatures(&self) -> Result<Vec<PackageSignature>, OoxmlError> {
+        PackageSignature::read_all(self)
+    }
+
+    /// Sign the package with `key` and add the signature as a new XML signature part,
+    /// creating the signature origin part and its package relationship if needed.
+    ///
+    /// The signature is written when the package is saved. Returns the name of the new
+    /// signature part, e.g. `/_xmlsignatures/sig1.xml`.
+    pub fn sign(&mut self, key: &SigningKey, options: &SignOptions) -> Result<PackUri, OoxmlError> {
+        let signature = signature::sign(self, key, options)?;
+
+        let origin = self
+            .relationships
+            .get_relationships_by_type(DIGITAL_SIGNATURE_ORIGIN_RELATIONSHIP_TYPE)
+            .find_map(|relationship| relationship.target_part(&PackUri::root()));
+        let origin = match origin {
+            Some(origin) => origin,
+            None => {
+                let origin = PackUri::new(DIGITAL_SIGNATURE_ORIGIN_PART);
+                self.relationships.add_relationship(Relationship::new(
+                    self.relationships.next_id(),
+                    DIGITAL_SIGNATURE_ORIGIN_RELATIONSHIP_TYPE,
+                    origin.zip_item_name(),
+                ));
+                origin
+            }
+        };
+        if !self.parts.contains_key(&origin) {
+            self.insert_part(
+                origin.clone(),
+                DIGITAL_SIGNATURE_ORIGIN_CONTENT_TYPE,
+                Vec::new(),
+            )?;
+        }
+
+        let part_name = (1..)
+            .map(|n| origin.resolve(&format!("sig{}.xml", n)))
+            .find(|part_name| !self.parts.contains_key(part_name))
+            .expect("unused signature part name");
+        self.insert_part(part_name.clone(), DIGITAL_SIGNATURE_CONTENT_TYPE, signature)?;
+        let relationships = self.part_relationships.entry(origin.clone()).or_default();
+        relationships.add_relationship(Relationship::new(
+            relationships.next_id(),
+            DIGITAL_SIGNATURE_RELATIONSHIP_TYPE,
+            origin.relative_target(&part_name),
+        ));
+        Ok(part_name)
+    }
+
+    /// The core properties of the package, e.g. title, creator and modification date.
+    pub fn properties(&self) -> &Properties {
+        &self.properties
+    }
+
+    /// Replace the core properties, rewriting the core properties part.
+    ///
+    /// The part and its package relationship are created if the package has none.
+    pub fn set_properties(&mut self, properties: Properties) -> Result<(), OoxmlError> {
+        let mut data = Vec::new();
+        properties.write(&mut data)?;
+        let data =
+            self.preserve_root_part(CORE_PROPERTIES_RELATIONSHIP_TYPE, data, PROPERTY_TAGS)?;
+        self.set_root_part(
+            CORE_PROPERTIES_RELATIONSHIP_TYPE,
+            CORE_PROPERTIES_URI,
+            CORE_PROPERTIES_CONTENT_TYPE,
+            data,
+        )?;
+        self.properties = properties;
+        Ok(())
+    }
+
+    /// The extended properties of the package, e.g. application, company and the titles
+    /// of the document parts.
+    pub fn app_properties(&self) -> &AppProperties {
+        &self.app_properties
+    }
+
+    /// Replace the extended properties, rewriting the extended properties part.
+    ///
+    /// The part and its package relationship are created if the package has none.
+    pub fn set_app_properties(&mut self, properties: AppProperties) -> Result<(), OoxmlError> {
+        let mut data = Vec::new();
+        properties.write(&mut data)?;
+        let data = self.preserve_root_part(
+            EXTENDED_PROPERTIES_RELATIONSHIP_TYPE,
+            data,
+            APP_PROPERTY_TAGS,
+        )?;
+        self.set_root_part(
+            EXTENDED_PROPERTIES_RELATIONSHIP_TYPE,
+            APP_PROPERTIES_URI,
+            APP_PROPERTIES_CONTENT_TYPE,
+            data,
+        )?;
+        self.app_properties = properties;
+        Ok(())
+    }
+
+    /// The custom properties of the package by name, empty if the package has no
+    /// custom properties part.
+    pub fn custom_properties(&self) -> LinkedHashMap<String, Variant> {
+        self.coustom_properties
+            .iter()
+            .flat_map(CustomProperties::iter)
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect()
+    }
+
+    /// Set the custom property `name`, rewriting the custom properties part.
+    ///
+    /// The part and its package relationship are created if the package has none.
+    pub fn set_custom_property<S: Into<String>, V: Into<Variant>>(
+        &mut self,
+        name: S,
+        value: V,
+    ) -> Result<(), OoxmlError> {
+        let mut properties = self.coustom_properties.clone().unwrap_or_default();
+        properties.set(name, value)?;
+        self.write_custom_properties(properties)
+    }
+
+    /// Remove the custom property `name`, rewriting the custom properties part.
+    pub fn remove_custom_property(&mut self, name: &str) -> Result<Option<Variant>, OoxmlError> {
+        let mut properties = match self.coustom_properties.clone() {
+            Some(properties) => properties,
+            None => return Ok(None),
+        };
+        let value = properties.remove(name);
+        if value.is_some() {
+            self.write_custom_properties(properties)?;
+        }
+        Ok(value)
+    }
+
+    fn write_custom_properties(&mut self, properties: CustomProperties) -> Result<(), OoxmlError> {
+        let mut data = Vec::new();
+        properties.write(&mut data)?;
+        let data = self.preserve_root_part(
+            CUSTOM_PROPERTIES_RELATIONSHIP_TYPE,
+            data,
+            &[CUSTOM_PROPERTY_TAG],
+        )?;
+        self.set_root_part(
+            CUSTOM_PROPERTIES_RELATIONSHIP_TYPE,
+            CUSTOM_PROPERTIES_URI,
+            CUSTOM_PROPERTIES_CONTENT_TYPE,
+            data,
+        )?;
+        self.coustom_properties = Some(properties);
+        Ok(())
+    }
+
+    /// The thumbnail image of the package and its content type, e.g. `image/jpeg`.
+    pub fn thumbnail(&self) -> Result<Option<(&[u8], &str)>, OoxmlError> {
+        let part = match self.get_related_part(THUMBNAIL_RELATIONSHIP_TYPE) {
+            Some(part) => part,
+            None => return Ok(None),
+        };
+        let content_type = part.content_type().map(String::as_str).unwrap_or_default();
+        Ok(Some((part.data()?, content_type)))
+    }
+
+    /// Set the thumbnail image, replacing any existing thumbnail.
+    ///
+    /// The part is named after the content type, e.g. `/docProps/thumbnail.jpeg` for
+    /// `image/jpeg`.
+    pub fn set_thumbnail(&mut self, data: Vec<u8>, content_type: &str) -> Result<(), OoxmlError> {
+        self.remove_thumbnail();
+        let extension = match content_type.to_ascii_lowercase().as_str() {
+            "image/jpeg" => "jpeg",
+            "image/png" => "png",
+            "image/gif" => "gif",
+            "image/bmp" => "bmp",
+            "image/tiff" => "tiff",
+            "image/x-wmf" => "wmf",
+            "image/x-emf" => "emf",
+            _ => "bin",
+        };
+        self.set_root_part(
+            THUMBNAIL_RELATIONSHIP_TYPE,
+            &format!("{}.{}", THUMBNAIL_PART_STEM, extension),
+            content_type,
+            data,
+        )?;
+        Ok(())
+    }
+
+    /// Remove the thumbnail image and its package relationship, returns `false` if the
+    /// package has no thumbnail.
+    pub fn remove_thumbnail(&mut self) -> bool {
+        let relationships: Vec<(String, PackUri)> = self
+            .relationships
+            .get_relationships_by_type(THUMBNAIL_RELATIONSHIP_TYPE)
+            .filter_map(|r| Some((r.id().to_string(), r.target_part(&PackUri::root())?)))
+            .collect();
+        for (id, part_name) in &relationships {
+            self.relationships.remove_relationship(id);
+            self.remove_part(part_name);
+        }
+        !relationships.is_empty()
+    }
+
+    /// Remove a part with its content type override and relationships.
+    fn remove_part(&mut self, name: &PackUri) -> Option<OpenXmlPart> {
+        self.content_types.remove_override_element(name);
+        self.part_relationships.remove(name);
+        self.parts.remove(name)
+    }
+
+    /// Keep the content of the current part of `relationship_type` that is not in `known`
+    /// in `data`, the part's new serialization.
+    fn preserve_root_part(
+        &self,
+        relationship_type: &str,
+        data: Vec<u8>,
+        known: &[&str],
+    ) -> Result<Vec<u8>, OoxmlError> {
+        match self.get_related_part(relationship_type) {
+            Some(part) => xml::preserve_unknown(part.data()?, data, known),
+            None => Ok(data),
+        }
+    }
+
+    /// Replace the data of the part targeted by the package relationship of
+    /// `relationship_type`, adding the relationship to a new `default_name` part if
+    /// there is none.
+    fn set_root_part(
+        &mut self,
+        relationship_type: &str,
+        default_name: &str,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> Result<PackUri, OoxmlError> {
+        let part_name = self
+            .relationships
+            .get_relationships_by_type(relationship_type)
+            .find_map(|relationship| relationship.target_part(&PackUri::root()));
+        let part_name = match part_name {
+            Some(part_name) => part_name,
+            None => {
+                let part_name = PackUri::new(default_name);
+                self.relationships.add_relationship(Relationship::new(
+                    self.relationships.next_id(),
+                    relationship_type,
+                    part_name.zip_item_name(),
+                ));
+                part_name
+            }
+        };
+        self.insert_part(part_name.clone(), content_type, data)?;
+        Ok(part_name)
+    }
+
+    /// Add a part with a content type override, replacing any part of the same name.
+    fn insert_part(
+        &mut self,
+        name: PackUri,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> Result<(), OoxmlError> {
+        let mut part = OpenXmlPart::from_reader(name.zip_item_name(), data.as_slice())?;
+        part.set_content_type(Some(content_type.to_string()));
+        let resolved = self.content_types.get_content_type(&name);
+        if !resolved.is_some_and(|resolved| content_type_eq(resolved, content_type)) {
+            self.content_types
+                .add_override_element(name.to_string(), content_type.to_string());
+        }
+        self.parts.insert(name, part);
+        Ok(())
+    }
+
+    /// Iterate all parts in package order, relationship parts excluded.
+    pub fn parts(&self) -> impl Iterator<Item = &OpenXmlPart> {
+        self.parts.values()
+    }
+
+    /// Iterate the package properties parts, i.e. the core, extended and custom
+    /// properties and the thumbnail targeted by package relationships.
+    pub fn property_parts(&self) -> impl Iterator<Item = &OpenXmlPart> {
+        const PROPERTY_RELATIONSHIP_TYPES: [&str; 4] = [
+            CORE_PROPERTIES_RELATIONSHIP_TYPE,
+            EXTENDED_PROPERTIES_RELATIONSHIP_TYPE,
+            CUSTOM_PROPERTIES_RELATIONSHIP_TYPE,
+            THUMBNAIL_RELATIONSHIP_TYPE,
+        ];
+        self.relationships
+            .iter()
+            .filter(|r| PROPERTY_RELATIONSHIP_TYPES.iter().any(|t| r.has_type(t)))
+            .filter_map(move |r| self.parts.get(&r.target_part(&PackUri::root())?))
+    }
+
+    /// Conformance class of the package, given by the type of its main document
+    /// relationship.
+    pub fn conformance(&self) -> Conformance {
+        self.relationships
+            .get_relationships_by_type(OFFICE_DOCUMENT_RELATIONSHIP_TYPE)
+            .map(|relationship| Conformance::of(relationship.relationship_type()))
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// Get the package root relationships.
+    pub fn relationships(&self) -> &Relationships {
+        &self.relationships
+    }
+
+    /// Iterate the relationships of every part that has any, keyed by source part.
+    pub fn part_relationships(&self) -> impl Iterator<Item = (&PackUri, &Relationships)> {
+        self.part_relationships.iter()
+    }
+
+    /// Get the relationships whose source is `part`, `"/"` means the package root.
+    pub fn relationships_of(&self, part: &str) -> Option<&Relationships> {
+        let part = PackUri::new(part);
+        if part.is_root() {
+            Some(&self.relationships)
+        } else {
+            self.part_relationships.get(&part)
+        }
+    }
+
+    /// Get the parts targeted by relationships of `relationship_type` from `part`.
+    ///
+    /// Combine with [`OpenXmlPackage::relationships_of`] to walk the package graph, e.g.
+    /// worksheet → drawing → chart → image.
+    pub fn related_parts(&self, part: &str, relationship_type: &str) -> Vec<&OpenXmlPart> {
+        let source = PackUri::new(part);
+        self.relationships_of(part)
+            .into_iter()
+            .flat_map(|relationships| relationships.get_relationships_by_type(relationship_type))
+            .filter_map(|relationship| relationship.target_part(&source))
+            .filter_map(|target| self.parts.get(&target))
+            .collect()
+    }
+
+    /// Get a part by its part name, e.g. `/xl/workbook.xml`.
+    ///
+    /// Part names are matched case-insensitively, the leading `/` is optional.
     pub fn get_part(&self, uri: &str) -> Option<&OpenXmlPart> {
-        self.parts.get(uri)
+        self.parts.get(&PackUri::new(uri))
+    }
+
+    /// Get all parts of content type `content_type`, e.g. every worksheet or image part.
+    pub fn parts_by_content_type<'a>(
+        &'a self,
+        content_type: &'a str,
+    ) -> impl Iterator<Item = &'a OpenXmlPart> {
+        self.parts.values().filter(move |part| {
+            part.content_type()
+                .is_some_and(|ct| content_type_eq(ct, content_type))
+        })
+    }
+
+    pub fn content_types(&self) -> &ContentTypes {
+        &self.content_types
     }
 
     pub fn has_content_types(&self) -> bool {
@@ -101,3 +958,77 @@ impl OpenXmlPackage {
         !self.relationships.is_empty()
     }
 }
+
+/// A zip entry, or the pieces of a part split into several entries.
+struct ZipEntry {
+    /// Zip item name, the name of the part for pieces.
+    name: String,
+    /// Archive indices of the entry or of the pieces in order.
+    indices: Vec<usize>,
+}
+
+/// Piece number, whether it is the last piece and archive index.
+type Piece = (u32, bool, usize);
+
+/// The entries of `zip` in archive order, directories excluded and the pieces of a part,
+/// e.g. `xl/worksheets/sheet1.xml/[0].piece` to `[1].last.piece`, grouped at the position
+/// of the first piece.
+fn zip_entries<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Result<Vec<ZipEntry>, OoxmlError> {
+    let mut entries = Vec::new();
+    // Part name, compared like part names → position in `entries` and pieces.
+    let mut pieced: HashMap<PackUri, (usize, Vec<Piece>)> = HashMap::new();
+    for index in 0..zip.len() {
+        let file = zip.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        let (name, piece, last) = match uri::piece(file.name()) {
+            Some(piece) => piece,
+            None => {
+                entries.push(ZipEntry {
+                    name: file.name().to_string(),
+                    indices: vec![index],
+                });
+                continue;
+            }
+        };
+        let position = entries.len();
+        let (_, pieces) = pieced
+            .entry(PackUri::new(name))
+            .or_insert_with(|| (position, Vec::new()));
+        if pieces.is_empty() {
+            entries.push(ZipEntry {
+                name: name.to_string(),
+                indices: Vec::new(),
+            });
+        }
+        pieces.push((piece, last, index));
+    }
+
+    for (_, (position, mut pieces)) in pieced {
+        pieces.sort_by_key(|&(piece, _, _)| piece);
+        let count = pieces.len();
+        for (expected, &(piece, last, _)) in pieces.iter().enumerate() {
+            let reason = if piece as usize != expected {
+                format!("piece {} is missing or duplicated", expected)
+            } else if last && expected + 1 != count {
+                format!("piece {} is marked last but is not", piece)
+            } else if !last && expected + 1 == count {
+                "the last piece is missing".to_string()
+            } else {
+                continue;
+            };
+            return Err(OoxmlError::InvalidPieces {
+                part: PackUri::new(&entries[position].name).to_string(),
+                reason,
+            });
+        }
+        entries[position].indices = pieces.into_iter().map(|(_, _, index)| index).collect();
+    }
+    Ok(entries)
+}
+
+fn utf8(data: Vec<u8>) -> Result<String, OoxmlError> {
+    String::from_utf8(data)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e).into())
+}
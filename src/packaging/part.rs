@@ -1,13 +1,45 @@
 use crate::error::OoxmlError;
 use crate::packaging::content_type::ContentType;
-use std::io::{Cursor, Read};
-use std::path::PathBuf;
+use crate::packaging::options::PartLimits;
+use crate::packaging::package::OpenXmlPackage;
+use crate::packaging::uri::PackUri;
+use crate::packaging::xml;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use zip::ZipArchive;
+
+pub(crate) trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Zip archive shared by all parts of a lazily opened package.
+pub(crate) type SharedArchive = Arc<Mutex<ZipArchive<Box<dyn ReadSeek>>>>;
+
+#[derive(Clone)]
+struct PartSource {
+    archive: SharedArchive,
+    index: usize,
+    limits: PartLimits,
+}
+
+impl std::fmt::Debug for PartSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PartSource")
+            .field("index", &self.index)
+            .finish()
+    }
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct OpenXmlPart {
     uri: PathBuf,
     content_type: Option<ContentType>,
-    raw: Cursor<Vec<u8>>,
+    size: Option<u64>,
+    compressed_size: Option<u64>,
+    raw: OnceLock<Vec<u8>>,
+    source: Option<PartSource>,
 }
 
 impl OpenXmlPart {
@@ -15,10 +47,10 @@ impl OpenXmlPart {
         uri: S,
         mut reader: R,
     ) -> Result<Self, OoxmlError> {
-        let mut raw = Cursor::new(Vec::new());
+        let mut raw = Vec::new();
         std::io::copy(&mut reader, &mut raw)?;
         let part = Self {
-            raw,
+            raw: OnceLock::from(raw),
             uri: uri.into(),
             ..Default::default()
         };
@@ -26,7 +58,171 @@ impl OpenXmlPart {
         Ok(part)
     }
 
+    pub(crate) fn from_data<S: Into<PathBuf>>(uri: S, data: Vec<u8>) -> Self {
+        Self {
+            raw: OnceLock::from(data),
+            uri: uri.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Create a part whose data stays compressed in `archive` until it is first read,
+    /// `limits` are checked then.
+    pub(crate) fn lazy<S: Into<PathBuf>>(
+        uri: S,
+        archive: SharedArchive,
+        index: usize,
+        limits: PartLimits,
+    ) -> Self {
+        Self {
+            uri: uri.into(),
+            source: Some(PartSource {
+                archive,
+                index,
+                limits,
+            }),
+            ..Default::default()
+        }
+    }
+
+    pub fn uri(&self) -> &Path {
+        &self.uri
+    }
+
+    /// The part name, e.g. `/xl/workbook.xml`.
+    pub fn name(&self) -> PackUri {
+        PackUri::new(self.uri.to_string_lossy())
+    }
+
+    /// Get the parts of `package` targeted by relationships of `relationship_type` from
+    /// this part.
+    pub fn related_parts<'a>(
+        &self,
+        package: &'a OpenXmlPackage,
+        relationship_type: &str,
+    ) -> Vec<&'a OpenXmlPart> {
+        package.related_parts(self.name().as_str(), relationship_type)
+    }
+
+    pub fn content_type(&self) -> Option<&ContentType> {
+        self.content_type.as_ref()
+    }
+
+    pub(crate) fn set_content_type(&mut self, content_type: Option<ContentType>) {
+        self.content_type = content_type;
+    }
+
+    pub(crate) fn set_zip_sizes(&mut self, size: u64, compressed_size: u64) {
+        self.size = Some(size);
+        self.compressed_size = Some(compressed_size);
+    }
+
+    /// The decompressed size of the part in bytes.
+    ///
+    /// For a part that is not loaded yet this is the size recorded in the zip directory.
+    pub fn size(&self) -> u64 {
+        match self.raw.get() {
+            Some(raw) => raw.len() as u64,
+            None => self.size.unwrap_or_default(),
+        }
+    }
+
+    /// The compressed size of the part in the zip archive it was read from, `None` for
+    /// parts added in memory.
+    pub fn compressed_size(&self) -> Option<u64> {
+        self.compressed_size
+    }
+
+    /// Whether the part data has been decompressed into memory.
+    pub fn is_loaded(&self) -> bool {
+        self.raw.get().is_some()
+    }
+
+    /// Get the part data, decompressing it from the archive on first access.
+    pub fn data(&self) -> Result<&[u8], OoxmlError> {
+        if let Some(raw) = self.raw.get() {
+            return Ok(raw);
+        }
+
+        let mut raw = Vec::new();
+        if let Some(source) = &self.source {
+            let mut archive = source
+                .archive
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            let mut file = archive.by_index(source.index)?;
+            raw = read_entry(&mut file, &source.limits, None)?;
+        }
+        Ok(self.raw.get_or_init(|| raw))
+    }
+
+    /// Get the part data.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the data of a lazy part cannot be read or exceeds a limit of the
+    /// [`OpenOptions`](super::options::OpenOptions).
+    #[deprecated(note = "use `data`, which returns read errors")]
     pub fn as_part_bytes(&self) -> &[u8] {
-        self.raw.get_ref()
+        self.data().expect("read part data from archive")
+    }
+}
+
+/// Decompress a zip entry, failing as soon as the data exceeds the part size or
+/// compression ratio limit.
+///
+/// Decompression also stops one byte past `remaining_total`, the package size budget
+/// left, for the caller to report the package as too large.
+pub(crate) fn read_entry(
+    file: &mut zip::read::ZipFile,
+    limits: &PartLimits,
+    remaining_total: Option<u64>,
+) -> Result<Vec<u8>, OoxmlError> {
+    let part = PackUri::new(file.name()).to_string();
+    let ratio_limit = limits
+        .max_compression_ratio
+        .map(|ratio| file.compressed_size().max(1).saturating_mul(ratio));
+    let limit = [limits.max_part_size, ratio_limit, remaining_total]
+        .iter()
+        .flatten()
+        .copied()
+        .min();
+
+    let mut raw = Vec::new();
+    match limit {
+        Some(limit) => {
+            file.take(limit.saturating_add(1)).read_to_end(&mut raw)?;
+        }
+        None => {
+            file.read_to_end(&mut raw)?;
+        }
+    }
+    let size = raw.len() as u64;
+    if let Some(limit) = limits.max_part_size.filter(|&limit| size > limit) {
+        return Err(OoxmlError::PartTooLarge { part, limit });
+    }
+    if let (Some(limit), Some(ratio_limit)) = (limits.max_compression_ratio, ratio_limit) {
+        if size > ratio_limit {
+            return Err(OoxmlError::CompressionRatioExceeded { part, limit });
+        }
     }
+    if let Some(limit) = limits.max_xml_depth {
+        if xml::exceeds_depth(&raw, limit) {
+            return Err(OoxmlError::XmlTooDeep { part, limit });
+        }
+    }
+    Ok(raw)
+}
+
+#[test]
+fn read_entry_stops_at_total_budget() {
+    use std::io::Write;
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    zip.start_file("zeros.txt", zip::write::FileOptions::default())
+        .unwrap();
+    zip.write_all(&[0u8; 1 << 20]).unwrap();
+    let mut zip = zip::ZipArchive::new(zip.finish().unwrap()).unwrap();
+    let mut file = zip.by_index(0).unwrap();
+    let data = read_entry(&mut file, &PartLimits::default(), Some(1000)).unwrap();
+    assert_eq!(data.len(), 1001);
 }
@@ -1,8 +1,12 @@
 use super::content_type::ContentType;
+use crate::error::OoxmlError;
+use crate::packaging::element::{OpenXmlDeserializeDefault, OpenXmlDeserialized};
 
-use serde::Deserialize;
-use serde::Serialize;
+use chrono::{NaiveDate, NaiveDateTime, SecondsFormat, TimeZone, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
 pub const CORE_PROPERTIES_URI: &str = "docProps/core.xml";
+pub const CORE_PROPERTIES_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-package.core-properties+xml";
 pub const CORE_PROPERTIES_NAMESPACE: &str =
     "http://schemas.openxmlformats.org/package/2006/metadata/core-properties";
 pub const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
@@ -11,30 +15,54 @@ pub const DCMITYPE_NAMESPACE: &str = "http://purl.org/dc/dcmitype/";
 pub const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
 
 pub const CORE_PROPERTIES_TAG: &str = "cp:coreProperties";
-pub const CORE_PROPERTIES_NAMESPACE_ATTRIBUTE: &str = "xmlns:dc";
+pub const CORE_PROPERTIES_NAMESPACE_ATTRIBUTE: &str = "xmlns:cp";
 pub const DC_NAMESPACE_ATTRIBUTE: &str = "xmlns:dc";
 pub const DCTERMS_NAMESPACE_ATTRIBUTE: &str = "xmlns:dcterms";
 pub const DCMITYPE_NAMESPACE_ATTRIBUTE: &str = "xmlns:dcmitype";
 pub const XSI_NAMESPACE_ATTRIBUTE: &str = "xmlns:xsi";
 
-pub const PROPERTY_CATEGORY_TAG: &str = "dc:creator";
-pub const PROPERTY_CONTENT_STATUS_TAG: &str = "dc:contentStatus";
-pub const PROPERTY_CONTENT_TYPE_TAG: &str = "dc:contentType";
+pub const PROPERTY_CATEGORY_TAG: &str = "cp:category";
+pub const PROPERTY_CONTENT_STATUS_TAG: &str = "cp:contentStatus";
+pub const PROPERTY_CONTENT_TYPE_TAG: &str = "cp:contentType";
 pub const PROPERTY_CREATED_TAG: &str = "dcterms:created";
 pub const PROPERTY_CREATOR_TAG: &str = "dc:creator";
 pub const PROPERTY_DESCRIPTION_TAG: &str = "dc:description";
 pub const PROPERTY_IDENTIFIER_TAG: &str = "dc:identifier";
-pub const PROPERTY_KEYWORDS_TAG: &str = "dc:keywords";
+pub const PROPERTY_KEYWORDS_TAG: &str = "cp:keywords";
 pub const PROPERTY_LANGUAGE_TAG: &str = "dc:language";
 pub const PROPERTY_MODIFIED_TAG: &str = "dcterms:modified";
 pub const PROPERTY_LAST_MODIFIED_BY_TAG: &str = "cp:lastModifiedBy";
 pub const PROPERTY_LAST_PRINTED_TAG: &str = "cp:lastPrinted";
 pub const PROPERTY_REVISION_TAG: &str = "cp:revision";
-pub const PROPERTY_SUBJECT_TAG: &str = "cp:subject";
-pub const PROPERTY_TITLE_TAG: &str = "cp:title";
+pub const PROPERTY_SUBJECT_TAG: &str = "dc:subject";
+pub const PROPERTY_TITLE_TAG: &str = "dc:title";
 pub const PROPERTY_VERSION_TAG: &str = "cp:version";
 
-pub type DateTime = String;
+/// Elements modelled by [`Properties`], others are kept when the part is rewritten.
+pub(crate) const PROPERTY_TAGS: &[&str] = &[
+    PROPERTY_CATEGORY_TAG,
+    PROPERTY_CONTENT_STATUS_TAG,
+    PROPERTY_CONTENT_TYPE_TAG,
+    PROPERTY_CREATED_TAG,
+    PROPERTY_CREATOR_TAG,
+    PROPERTY_DESCRIPTION_TAG,
+    PROPERTY_IDENTIFIER_TAG,
+    PROPERTY_KEYWORDS_TAG,
+    PROPERTY_LANGUAGE_TAG,
+    PROPERTY_MODIFIED_TAG,
+    PROPERTY_LAST_MODIFIED_BY_TAG,
+    PROPERTY_LAST_PRINTED_TAG,
+    PROPERTY_REVISION_TAG,
+    PROPERTY_SUBJECT_TAG,
+    PROPERTY_TITLE_TAG,
+    PROPERTY_VERSION_TAG,
+];
+
+pub const XSI_TYPE_ATTRIBUTE: &str = "xsi:type";
+/// The `xsi:type` of the `dcterms` date properties.
+pub const W3CDTF_TYPE: &str = "dcterms:W3CDTF";
+
+pub type DateTime = chrono::DateTime<Utc>;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all(deserialize = "camelCase"))]
@@ -42,14 +70,17 @@ pub struct Properties {
     pub category: Option<String>,
     pub content_status: Option<String>,
     pub content_type: Option<ContentType>,
+    #[serde(default, deserialize_with = "deserialize_date_time")]
     pub created: Option<DateTime>,
     pub creator: Option<String>,
     pub description: Option<String>,
     pub identifier: Option<String>,
     pub keywords: Option<String>,
     pub language: Option<String>,
-    pub modified: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_date_time")]
+    pub modified: Option<DateTime>,
     pub last_modified_by: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_date_time")]
     pub last_printed: Option<DateTime>,
     pub revision: Option<String>,
     pub subject: Option<String>,
@@ -57,8 +88,201 @@ pub struct Properties {
     pub version: Option<String>,
 }
 
+macro_rules! setters {
+    ($($setter:ident => $field:ident),* $(,)?) => {
+        $(
+            pub fn $setter<S: Into<String>>(&mut self, value: S) {
+                self.$field = Some(value.into());
+            }
+        )*
+    };
+}
+
 impl Properties {
-    pub fn parse_from_xml_str(reader: &str) -> Self {
-        quick_xml::de::from_str(reader).unwrap()
+    pub fn parse_from_xml_str(reader: &str) -> Result<Self, OoxmlError> {
+        Self::from_xml_str(reader)
+    }
+
+    setters! {
+        set_category => category,
+        set_content_status => content_status,
+        set_content_type => content_type,
+        set_creator => creator,
+        set_description => description,
+        set_identifier => identifier,
+        set_keywords => keywords,
+        set_language => language,
+        set_last_modified_by => last_modified_by,
+        set_revision => revision,
+        set_subject => subject,
+        set_title => title,
+        set_version => version,
+    }
+
+    pub fn set_created(&mut self, created: DateTime) {
+        self.created = Some(created);
     }
+
+    pub fn set_modified(&mut self, modified: DateTime) {
+        self.modified = Some(modified);
+    }
+
+    pub fn set_last_printed(&mut self, last_printed: DateTime) {
+        self.last_printed = Some(last_printed);
+    }
+
+    /// Serialize the properties as a core properties part (ECMA-376 Part 2, 11).
+    ///
+    /// Unset properties are omitted, dates are written as `dcterms:W3CDTF` in UTC.
+    pub fn write<W: std::io::Write>(&self, writer: W) -> Result<(), OoxmlError> {
+        let mut xml = quick_xml::Writer::new(writer);
+        use quick_xml::events::*;
+
+        xml.write_event(Event::Decl(BytesDecl::new(
+            b"1.0",
+            Some(b"UTF-8"),
+            Some(b"yes"),
+        )))?;
+
+        let elem = BytesStart::borrowed_name(CORE_PROPERTIES_TAG.as_bytes()).with_attributes(vec![
+            (
+                CORE_PROPERTIES_NAMESPACE_ATTRIBUTE,
+                CORE_PROPERTIES_NAMESPACE,
+            ),
+            (DC_NAMESPACE_ATTRIBUTE, DC_NAMESPACE),
+            (DCTERMS_NAMESPACE_ATTRIBUTE, DCTERMS_NAMESPACE),
+            (DCMITYPE_NAMESPACE_ATTRIBUTE, DCMITYPE_NAMESPACE),
+            (XSI_NAMESPACE_ATTRIBUTE, XSI_NAMESPACE),
+        ]);
+        xml.write_event(Event::Start(elem))?;
+
+        let date = |value: &Option<DateTime>| {
+            value
+                .as_ref()
+                .map(|value| value.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        };
+        let properties = [
+            (PROPERTY_CATEGORY_TAG, self.category.clone(), false),
+            (
+                PROPERTY_CONTENT_STATUS_TAG,
+                self.content_status.clone(),
+                false,
+            ),
+            (PROPERTY_CONTENT_TYPE_TAG, self.content_type.clone(), false),
+            (PROPERTY_CREATED_TAG, date(&self.created), true),
+            (PROPERTY_CREATOR_TAG, self.creator.clone(), false),
+            (PROPERTY_DESCRIPTION_TAG, self.description.clone(), false),
+            (PROPERTY_IDENTIFIER_TAG, self.identifier.clone(), false),
+            (PROPERTY_KEYWORDS_TAG, self.keywords.clone(), false),
+            (PROPERTY_LANGUAGE_TAG, self.language.clone(), false),
+            (
+                PROPERTY_LAST_MODIFIED_BY_TAG,
+                self.last_modified_by.clone(),
+                false,
+            ),
+            (PROPERTY_LAST_PRINTED_TAG, date(&self.last_printed), false),
+            (PROPERTY_MODIFIED_TAG, date(&self.modified), true),
+            (PROPERTY_REVISION_TAG, self.revision.clone(), false),
+            (PROPERTY_SUBJECT_TAG, self.subject.clone(), false),
+            (PROPERTY_TITLE_TAG, self.title.clone(), false),
+            (PROPERTY_VERSION_TAG, self.version.clone(), false),
+        ];
+        for (tag, value, w3cdtf) in properties.iter() {
+            let value = match value {
+                Some(value) => value,
+                None => continue,
+            };
+            let mut elem = BytesStart::borrowed_name(tag.as_bytes());
+            if *w3cdtf {
+                elem.push_attribute((XSI_TYPE_ATTRIBUTE, W3CDTF_TYPE));
+            }
+            xml.write_event(Event::Start(elem))?;
+            xml.write_event(Event::Text(BytesText::from_plain_str(value)))?;
+            xml.write_event(Event::End(BytesEnd::borrowed(tag.as_bytes())))?;
+        }
+
+        xml.write_event(Event::End(BytesEnd::borrowed(
+            CORE_PROPERTIES_TAG.as_bytes(),
+        )))?;
+        Ok(())
+    }
+}
+
+/// Parse a W3C date and time (W3CDTF), which may omit the time, the month or the day.
+///
+/// Values without a time zone are taken as UTC.
+pub fn parse_w3cdtf(value: &str) -> Option<DateTime> {
+    let value = value.trim();
+    if let Ok(date_time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(date_time.with_timezone(&Utc));
+    }
+    // `hh:mm` without seconds is allowed by W3CDTF but not by RFC 3339.
+    if let Some((date, time)) = value.split_once('T') {
+        // Slicing by `get` keeps non-ASCII input from splitting a character.
+        if let (Some(hours_minutes), Some(rest)) = (time.get(..5), time.get(5..)) {
+            if !rest.is_empty() && time.as_bytes()[2] == b':' && !rest.starts_with(':') {
+                return parse_w3cdtf(&format!("{}T{}:00{}", date, hours_minutes, rest));
+            }
+        }
+    }
+    let local = value.trim_end_matches('Z');
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(local, "%Y-%m-%dT%H:%M:%S%.f") {
+        return Some(Utc.from_utc_datetime(&date_time));
+    }
+    if let Ok(date_time) = NaiveDateTime::parse_from_str(local, "%Y-%m-%dT%H:%M") {
+        return Some(Utc.from_utc_datetime(&date_time));
+    }
+    let date = match value.len() {
+        4 => NaiveDate::from_ymd_opt(value.parse().ok()?, 1, 1),
+        7 => NaiveDate::parse_from_str(&format!("{}-01", value), "%Y-%m-%d").ok(),
+        _ => NaiveDate::parse_from_str(value, "%Y-%m-%d").ok(),
+    }?;
+    Some(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?))
+}
+
+fn deserialize_date_time<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    parse_w3cdtf(&value)
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid W3CDTF date {:?}", value)))
+}
+
+impl OpenXmlDeserializeDefault for Properties {
+    const UNDERSTOOD_NAMESPACES: &'static [&'static str] = &[
+        DC_NAMESPACE,
+        DCTERMS_NAMESPACE,
+        DCMITYPE_NAMESPACE,
+        XSI_NAMESPACE,
+    ];
+}
+
+#[test]
+fn w3cdtf_dates() {
+    let date = |y, m, d, h, min, s| Utc.with_ymd_and_hms(y, m, d, h, min, s).unwrap();
+    assert_eq!(
+        parse_w3cdtf("2021-07-07T02:32:26Z"),
+        Some(date(2021, 7, 7, 2, 32, 26))
+    );
+    assert_eq!(
+        parse_w3cdtf("2021-07-07T04:32:26+02:00"),
+        Some(date(2021, 7, 7, 2, 32, 26))
+    );
+    assert_eq!(
+        parse_w3cdtf("2021-07-07T02:32+00:00"),
+        Some(date(2021, 7, 7, 2, 32, 0))
+    );
+    assert_eq!(
+        parse_w3cdtf("2021-07-07T02:32:26"),
+        Some(date(2021, 7, 7, 2, 32, 26))
+    );
+    assert_eq!(parse_w3cdtf("2021-07-07"), Some(date(2021, 7, 7, 0, 0, 0)));
+    assert_eq!(parse_w3cdtf("2021-07"), Some(date(2021, 7, 1, 0, 0, 0)));
+    assert_eq!(parse_w3cdtf("2021"), Some(date(2021, 1, 1, 0, 0, 0)));
+    assert_eq!(parse_w3cdtf("2021-01-01T12:0é0"), None);
+    assert_eq!(parse_w3cdtf("yesterday"), None);
 }
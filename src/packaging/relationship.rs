@@ -1,21 +1,52 @@
+use std::fmt::Display;
 use std::io::BufRead;
 
 use linked_hash_map::LinkedHashMap;
-use serde::de::MapAccess;
-use serde::de::Visitor;
+use serde::de::{Error, IgnoredAny, MapAccess, Visitor};
 use serde::Deserialize;
 
-pub const RELATIONSHIPS_FILE: &'static str = "_rels/.rels";
-const XMLNS_ATTRIBUTE_NAME: &'static str = "xmlns";
-const RELATIONSHIP_NAMESPACE_URI: &'static str =
+use crate::error::OoxmlError;
+use crate::packaging::element::{OpenXmlDeserializeDefault, OpenXmlDeserialized};
+use crate::packaging::namespace::namespace_eq;
+use crate::packaging::uri::PackUri;
+
+pub const RELATIONSHIPS_FILE: &str = "_rels/.rels";
+
+pub const OFFICE_DOCUMENT_RELATIONSHIP_TYPE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument";
+pub const CORE_PROPERTIES_RELATIONSHIP_TYPE: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties";
+pub const EXTENDED_PROPERTIES_RELATIONSHIP_TYPE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties";
+pub const CUSTOM_PROPERTIES_RELATIONSHIP_TYPE: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/custom-properties";
+pub const THUMBNAIL_RELATIONSHIP_TYPE: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships/metadata/thumbnail";
+const XMLNS_ATTRIBUTE_NAME: &str = "xmlns";
+pub(crate) const RELATIONSHIP_NAMESPACE_URI: &str =
     "http://schemas.openxmlformats.org/package/2006/relationships";
-// const XMLNS_R_ATTRIBUTE_NAME: &'static str = "xmlns:r";
-const RELATIONSHIP_TAG_NAME: &'static str = "Relationship";
-const RELATIONSHIPS_TAG_NAME: &'static str = "Relationships";
+// const XMLNS_R_ATTRIBUTE_NAME: &str = "xmlns:r";
+const RELATIONSHIP_TAG_NAME: &str = "Relationship";
+const RELATIONSHIPS_TAG_NAME: &str = "Relationships";
+/// Elements modelled by [`Relationships`], others are kept when the part is rewritten.
+pub(crate) const RELATIONSHIPS_TAGS: &[&str] = &[RELATIONSHIP_TAG_NAME];
+const ID_ATTRIBUTE_NAME: &str = "Id";
+const TYPE_ATTRIBUTE_NAME: &str = "Type";
+const TARGET_ATTRIBUTE_NAME: &str = "Target";
+const TARGET_MODE_ATTRIBUTE_NAME: &str = "TargetMode";
 
-#[derive(Debug, PartialEq, Default, Clone)]
+#[derive(Debug, Default, Clone)]
 pub struct Relationships {
     relationships: LinkedHashMap<String, Relationship>,
+    duplicate_ids: Vec<String>,
+}
+
+/// Relationships are equal when they hold the same relationships, the duplicate ids
+/// seen while parsing are a diagnostic only.
+impl PartialEq for Relationships {
+    fn eq(&self, other: &Self) -> bool {
+        self.relationships == other.relationships
+    }
 }
 
 #[derive(Debug, PartialEq, Default, Clone, serde::Deserialize, serde::Serialize)]
@@ -24,26 +55,205 @@ pub struct Relationship {
     id: String,
     r#type: String,
     target: String,
+    #[serde(default, deserialize_with = "deserialize_target_mode")]
+    target_mode: TargetMode,
+}
+
+/// Whether a relationship targets a part of the package or an external resource.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize)]
+pub enum TargetMode {
+    #[default]
+    Internal,
+    External,
+}
+
+impl TargetMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TargetMode::Internal => "Internal",
+            TargetMode::External => "External",
+        }
+    }
+}
+
+impl Display for TargetMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn deserialize_target_mode<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<TargetMode, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    match value.as_str() {
+        "Internal" => Ok(TargetMode::Internal),
+        "External" => Ok(TargetMode::External),
+        _ => Err(D::Error::custom(format!("invalid TargetMode {:?}", value))),
+    }
 }
 
 impl Relationship {
+    pub fn new<I: Into<String>, T: Into<String>, U: Into<String>>(
+        id: I,
+        relationship_type: T,
+        target: U,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            r#type: relationship_type.into(),
+            target: target.into(),
+            target_mode: TargetMode::Internal,
+        }
+    }
+
+    /// Create a relationship to an external resource, e.g. a hyperlink.
+    pub fn new_external<I: Into<String>, T: Into<String>>(
+        id: I,
+        relationship_type: T,
+        target: &url::Url,
+    ) -> Self {
+        Self {
+            target_mode: TargetMode::External,
+            ..Self::new(id, relationship_type, target.as_str())
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn relationship_type(&self) -> &str {
+        &self.r#type
+    }
+
+    /// Whether the relationship is of `relationship_type`, its Strict and Transitional
+    /// spellings being equivalent.
+    pub fn has_type(&self, relationship_type: &str) -> bool {
+        namespace_eq(&self.r#type, relationship_type)
+    }
+
     pub fn target(&self) -> &str {
         &self.target
     }
+
+    pub fn target_mode(&self) -> TargetMode {
+        self.target_mode
+    }
+
+    pub fn is_external(&self) -> bool {
+        self.target_mode == TargetMode::External
+    }
+
+    /// The target of an external relationship as a URL, `None` for internal relationships
+    /// and relative external targets.
+    pub fn external_url(&self) -> Option<url::Url> {
+        if !self.is_external() {
+            return None;
+        }
+        url::Url::parse(&self.target).ok()
+    }
+
+    /// Resolve the target part of an internal relationship from `source`, `None` for
+    /// external relationships.
+    pub fn target_part(&self, source: &PackUri) -> Option<PackUri> {
+        if self.is_external() {
+            return None;
+        }
+        Some(source.resolve(&self.target))
+    }
+}
+
+impl Display for Relationships {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut container = Vec::new();
+        let mut cursor = std::io::Cursor::new(&mut container);
+        self.write(&mut cursor).expect("write xml to memory error");
+        let s = String::from_utf8_lossy(&container);
+        write!(f, "{}", s)?;
+        Ok(())
+    }
 }
 
 impl Relationships {
-    pub fn parse_from_xml_reader<R: BufRead>(reader: R) -> Self {
-        quick_xml::de::from_reader(reader).unwrap()
+    pub fn parse_from_xml_reader<R: BufRead>(reader: R) -> Result<Self, OoxmlError> {
+        Self::from_xml_reader(reader)
     }
 
-    pub fn parse_from_xml_str(reader: &str) -> Self {
-        quick_xml::de::from_str(reader).unwrap()
+    pub fn parse_from_xml_str(reader: &str) -> Result<Self, OoxmlError> {
+        Self::from_xml_str(reader)
     }
 
+    /// Add a relationship. When its id is already used the relationship is ignored and
+    /// the id is recorded in [`Relationships::duplicate_ids`].
     pub fn add_relationship(&mut self, relationship: Relationship) {
-        self.relationships
-            .insert(relationship.id.clone(), relationship);
+        if self.relationships.contains_key(&relationship.id) {
+            self.duplicate_ids.push(relationship.id);
+        } else {
+            self.relationships
+                .insert(relationship.id.clone(), relationship);
+        }
+    }
+
+    pub fn remove_relationship(&mut self, id: &str) -> Option<Relationship> {
+        self.relationships.remove(id)
+    }
+
+    /// Get the first `rIdN` id that is not used yet.
+    pub fn next_id(&self) -> String {
+        (1..)
+            .map(|n| format!("rId{}", n))
+            .find(|id| !self.relationships.contains_key(id))
+            .expect("unused relationship id")
+    }
+
+    /// Ids that were used by more than one relationship, only the first one is kept.
+    pub fn duplicate_ids(&self) -> &[String] {
+        &self.duplicate_ids
+    }
+
+    pub fn write<W: std::io::Write>(&self, writer: W) -> Result<(), OoxmlError> {
+        let mut xml = quick_xml::Writer::new(writer);
+        use quick_xml::events::attributes::Attribute;
+        use quick_xml::events::*;
+
+        // 1. write decl
+        xml.write_event(Event::Decl(BytesDecl::new(
+            b"1.0",
+            Some(b"UTF-8"),
+            Some(b"yes"),
+        )))?;
+
+        // 2. start relationships element
+        let mut elem = BytesStart::borrowed_name(RELATIONSHIPS_TAG_NAME.as_bytes());
+        let ns = Attribute {
+            key: XMLNS_ATTRIBUTE_NAME.as_bytes(),
+            value: RELATIONSHIP_NAMESPACE_URI.as_bytes().into(),
+        };
+        elem.extend_attributes(vec![ns]);
+        xml.write_event(Event::Start(elem))?;
+
+        // 3. write relationship entries
+        for relationship in self.relationships.values() {
+            let mut elem = BytesStart::borrowed_name(RELATIONSHIP_TAG_NAME.as_bytes())
+                .with_attributes(vec![
+                    Attribute::from((ID_ATTRIBUTE_NAME, relationship.id.as_str())),
+                    Attribute::from((TYPE_ATTRIBUTE_NAME, relationship.r#type.as_str())),
+                    Attribute::from((TARGET_ATTRIBUTE_NAME, relationship.target.as_str())),
+                ]);
+            if relationship.is_external() {
+                elem.push_attribute((
+                    TARGET_MODE_ATTRIBUTE_NAME,
+                    relationship.target_mode.as_str(),
+                ));
+            }
+            xml.write_event(Event::Empty(elem))?;
+        }
+
+        // 4. ends relationships element.
+        let end = BytesEnd::borrowed(RELATIONSHIPS_TAG_NAME.as_bytes());
+        xml.write_event(Event::End(end))?;
+        Ok(())
     }
 
     pub fn is_empty(&self) -> bool {
@@ -53,6 +263,21 @@ impl Relationships {
     pub fn get_relationship_by_id(&self, id: &str) -> Option<&Relationship> {
         self.relationships.get(id)
     }
+
+    pub fn get_relationships_by_type<'a>(
+        &'a self,
+        relationship_type: &'a str,
+    ) -> impl Iterator<Item = &'a Relationship> {
+        self.iter().filter(move |r| r.has_type(relationship_type))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Relationship> {
+        self.relationships.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.relationships.len()
+    }
 }
 
 impl<'de> Deserialize<'de> for Relationships {
@@ -64,6 +289,8 @@ impl<'de> Deserialize<'de> for Relationships {
     }
 }
 
+impl OpenXmlDeserializeDefault for Relationships {}
+
 struct RelationshipsVisitor;
 
 impl<'de> Visitor<'de> for RelationshipsVisitor {
@@ -86,7 +313,7 @@ impl<'de> Visitor<'de> for RelationshipsVisitor {
                 }
 
                 s if s == RELATIONSHIPS_TAG_NAME => {
-                    unreachable!();
+                    return Err(M::Error::custom("unexpected nested Relationships element"));
                 }
 
                 s if s == RELATIONSHIP_TAG_NAME => {
@@ -94,11 +321,54 @@ impl<'de> Visitor<'de> for RelationshipsVisitor {
                     types.add_relationship(v);
                 }
                 _ => {
-                    println!("{}", key);
-                    unreachable!("relationships unsupport!");
+                    log::debug!("unrecognized relationships element: {}", key);
+                    let _: IgnoredAny = access.next_value()?;
                 }
             }
         }
         Ok(types)
     }
 }
+
+#[test]
+fn external_relationships() {
+    let raw = r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" Target="https://example.com/a%20b" TargetMode="External"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="../media/image1.png"/></Relationships>"#;
+    let relationships = Relationships::parse_from_xml_str(raw).unwrap();
+    let source = PackUri::new("/xl/worksheets/sheet1.xml");
+
+    let hyperlink = relationships.get_relationship_by_id("rId1").unwrap();
+    assert_eq!(hyperlink.target_mode(), TargetMode::External);
+    assert_eq!(
+        hyperlink.external_url().map(String::from),
+        Some("https://example.com/a%20b".to_string())
+    );
+    assert_eq!(hyperlink.target_part(&source), None);
+
+    let image = relationships.get_relationship_by_id("rId2").unwrap();
+    assert_eq!(image.target_mode(), TargetMode::Internal);
+    assert_eq!(image.external_url(), None);
+    assert_eq!(
+        image.target_part(&source),
+        Some(PackUri::new("/xl/media/image1.png"))
+    );
+
+    let written = Relationships::parse_from_xml_str(&relationships.to_string()).unwrap();
+    assert_eq!(written, relationships);
+    assert!(relationships
+        .to_string()
+        .contains(r#"TargetMode="External""#));
+
+    let raw = r#"<Relationships><Relationship Id="rId1" Type="t" Target="first"/><Relationship Id="rId1" Type="t" Target="second"/></Relationships>"#;
+    let duplicated = Relationships::parse_from_xml_str(raw).unwrap();
+    assert_eq!(duplicated.duplicate_ids(), ["rId1"]);
+    let relationship = duplicated.get_relationship_by_id("rId1").unwrap();
+    assert_eq!(relationship.target(), "first");
+    let written = Relationships::parse_from_xml_str(&duplicated.to_string()).unwrap();
+    assert!(written.duplicate_ids().is_empty());
+    assert_eq!(written, duplicated);
+
+    assert!(Relationships::parse_from_xml_str(
+        r#"<Relationships><Relationship Id="rId1" Type="t" Target="x" TargetMode="Remote"/></Relationships>"#
+    )
+    .is_err());
+}
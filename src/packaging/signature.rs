@@ -0,0 +1,870 @@
+//! OPC digital signatures (ECMA-376 Part 2, 13).
+//!
+//! A signed package has an origin part, `/_xmlsignatures/origin.sigs`, targeted by the
+//! package relationships. Every XML signature part, e.g. `/_xmlsignatures/sig1.xml`, is
+//! targeted by a relationship of the origin part.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey};
+use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use x509_cert::der::{Decode, DecodePem, Encode};
+use x509_cert::Certificate;
+
+use crate::error::OoxmlError;
+use crate::packaging::content_type::content_type_eq;
+use crate::packaging::package::OpenXmlPackage;
+use crate::packaging::part::OpenXmlPart;
+use crate::packaging::relationship::{Relationships, RELATIONSHIP_NAMESPACE_URI};
+use crate::packaging::uri::PackUri;
+use crate::packaging::xml::{C14nMode, NamespaceScope, XmlAttributes, XmlElement};
+
+pub const DIGITAL_SIGNATURE_ORIGIN_RELATIONSHIP_TYPE: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships/digital-signature/origin";
+pub const DIGITAL_SIGNATURE_RELATIONSHIP_TYPE: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships/digital-signature/signature";
+pub const DIGITAL_SIGNATURE_CERTIFICATE_RELATIONSHIP_TYPE: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships/digital-signature/certificate";
+
+pub const DIGITAL_SIGNATURE_ORIGIN_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-package.digital-signature-origin";
+pub const DIGITAL_SIGNATURE_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-package.digital-signature-xmlsignature+xml";
+pub const DIGITAL_SIGNATURE_CERTIFICATE_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-package.digital-signature-certificate";
+pub const RELATIONSHIPS_CONTENT_TYPE: &str =
+    "application/vnd.openxmlformats-package.relationships+xml";
+
+const XMLDSIG_NAMESPACE_URI: &str = "http://www.w3.org/2000/09/xmldsig#";
+const PACKAGE_SIGNATURE_NAMESPACE_URI: &str =
+    "http://schemas.openxmlformats.org/package/2006/digital-signature";
+const C14N_ALGORITHM: &str = "http://www.w3.org/TR/2001/REC-xml-c14n-20010315";
+const EXCLUSIVE_C14N_ALGORITHM: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const RELATIONSHIP_TRANSFORM_ALGORITHM: &str =
+    "http://schemas.openxmlformats.org/package/2006/RelationshipTransform";
+
+/// Digest algorithms supported in signature and digest methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DigestAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    fn from_digest_method(uri: &str) -> Option<Self> {
+        match uri {
+            "http://www.w3.org/2000/09/xmldsig#sha1" => Some(Self::Sha1),
+            "http://www.w3.org/2001/04/xmlenc#sha256" => Some(Self::Sha256),
+            "http://www.w3.org/2001/04/xmldsig-more#sha384" => Some(Self::Sha384),
+            "http://www.w3.org/2001/04/xmlenc#sha512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    fn from_signature_method(uri: &str) -> Option<Self> {
+        match uri {
+            "http://www.w3.org/2000/09/xmldsig#rsa-sha1" => Some(Self::Sha1),
+            "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256" => Some(Self::Sha256),
+            "http://www.w3.org/2001/04/xmldsig-more#rsa-sha384" => Some(Self::Sha384),
+            "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512" => Some(Self::Sha512),
+            _ => None,
+        }
+    }
+
+    fn digest_method(self) -> &'static str {
+        match self {
+            Self::Sha1 => "http://www.w3.org/2000/09/xmldsig#sha1",
+            Self::Sha256 => "http://www.w3.org/2001/04/xmlenc#sha256",
+            Self::Sha384 => "http://www.w3.org/2001/04/xmldsig-more#sha384",
+            Self::Sha512 => "http://www.w3.org/2001/04/xmlenc#sha512",
+        }
+    }
+
+    fn signature_method(self) -> &'static str {
+        match self {
+            Self::Sha1 => "http://www.w3.org/2000/09/xmldsig#rsa-sha1",
+            Self::Sha256 => "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256",
+            Self::Sha384 => "http://www.w3.org/2001/04/xmldsig-more#rsa-sha384",
+            Self::Sha512 => "http://www.w3.org/2001/04/xmldsig-more#rsa-sha512",
+        }
+    }
+
+    pub(crate) fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Sha1 => Sha1::digest(data).to_vec(),
+            Self::Sha256 => Sha256::digest(data).to_vec(),
+            Self::Sha384 => Sha384::digest(data).to_vec(),
+            Self::Sha512 => Sha512::digest(data).to_vec(),
+        }
+    }
+
+    pub(crate) fn pkcs1v15(self) -> Pkcs1v15Sign {
+        match self {
+            Self::Sha1 => Pkcs1v15Sign::new::<Sha1>(),
+            Self::Sha256 => Pkcs1v15Sign::new::<Sha256>(),
+            Self::Sha384 => Pkcs1v15Sign::new::<Sha384>(),
+            Self::Sha512 => Pkcs1v15Sign::new::<Sha512>(),
+        }
+    }
+}
+
+/// The X.509 certificate of a signer.
+#[derive(Debug, Clone)]
+pub struct SignerCertificate {
+    subject: String,
+    issuer: String,
+    serial_number: String,
+    not_before: DateTime<Utc>,
+    not_after: DateTime<Utc>,
+    der: Vec<u8>,
+}
+
+impl SignerCertificate {
+    pub fn from_der(der: &[u8]) -> Result<Self, OoxmlError> {
+        let certificate = Certificate::from_der(der).map_err(|e| invalid("certificate", e))?;
+        let tbs = &certificate.tbs_certificate;
+        let time = |time: &x509_cert::time::Time| {
+            let duration = time.to_unix_duration();
+            DateTime::from_timestamp(duration.as_secs() as i64, duration.subsec_nanos())
+                .unwrap_or_default()
+        };
+        Ok(Self {
+            subject: tbs.subject.to_string(),
+            issuer: tbs.issuer.to_string(),
+            serial_number: hex(tbs.serial_number.as_bytes()),
+            not_before: time(&tbs.validity.not_before),
+            not_after: time(&tbs.validity.not_after),
+            der: der.to_vec(),
+        })
+    }
+
+    /// Subject distinguished name in RFC 4514 form, e.g. `CN=Jane Doe,O=Example`.
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// Issuer distinguished name.
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// Serial number as upper-case hex.
+    pub fn serial_number(&self) -> &str {
+        &self.serial_number
+    }
+
+    pub fn not_before(&self) -> DateTime<Utc> {
+        self.not_before
+    }
+
+    pub fn not_after(&self) -> DateTime<Utc> {
+        self.not_after
+    }
+
+    /// The DER encoded certificate.
+    pub fn der(&self) -> &[u8] {
+        &self.der
+    }
+
+    fn public_key(&self) -> Result<RsaPublicKey, OoxmlError> {
+        let certificate =
+            Certificate::from_der(&self.der).map_err(|e| invalid("certificate", e))?;
+        let spki = certificate
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()
+            .map_err(|e| invalid("certificate", e))?;
+        RsaPublicKey::from_public_key_der(&spki).map_err(|e| invalid("certificate", e))
+    }
+}
+
+/// A private key and the certificate it belongs to, used to sign packages.
+#[derive(Debug, Clone)]
+pub struct SigningKey {
+    private_key: RsaPrivateKey,
+    certificate: SignerCertificate,
+}
+
+impl SigningKey {
+    /// Load a PEM certificate and its PEM private key, PKCS#8 (`BEGIN PRIVATE KEY`) or
+    /// PKCS#1 (`BEGIN RSA PRIVATE KEY`).
+    pub fn from_pem(certificate: &str, private_key: &str) -> Result<Self, OoxmlError> {
+        let der = Certificate::from_pem(certificate)
+            .and_then(|certificate| certificate.to_der())
+            .map_err(|e| invalid("certificate", e))?;
+        let private_key = RsaPrivateKey::from_pkcs8_pem(private_key)
+            .or_else(|_| RsaPrivateKey::from_pkcs1_pem(private_key))
+            .map_err(|e| invalid("private key", e))?;
+        Self::new(private_key, SignerCertificate::from_der(&der)?)
+    }
+
+    /// Load the private key and its certificate from a PKCS#12 (`.pfx`/`.p12`) file.
+    pub fn from_pkcs12(pkcs12: &[u8], password: &str) -> Result<Self, OoxmlError> {
+        let key_store = p12_keystore::KeyStore::from_pkcs12(pkcs12, password)
+            .map_err(|e| invalid("PKCS#12 file", e))?;
+        let (_, chain) = key_store
+            .private_key_chain()
+            .ok_or_else(|| invalid("PKCS#12 file", "no private key"))?;
+        let private_key =
+            RsaPrivateKey::from_pkcs8_der(chain.key()).map_err(|e| invalid("private key", e))?;
+        let certificate = chain
+            .chain()
+            .first()
+            .ok_or_else(|| invalid("PKCS#12 file", "no certificate"))?;
+        Self::new(
+            private_key,
+            SignerCertificate::from_der(certificate.as_der())?,
+        )
+    }
+
+    fn new(private_key: RsaPrivateKey, certificate: SignerCertificate) -> Result<Self, OoxmlError> {
+        if RsaPublicKey::from(&private_key) != certificate.public_key()? {
+            return Err(invalid("private key", "does not match the certificate"));
+        }
+        Ok(Self {
+            private_key,
+            certificate,
+        })
+    }
+
+    pub fn certificate(&self) -> &SignerCertificate {
+        &self.certificate
+    }
+}
+
+/// Options used by [`OpenXmlPackage::sign`].
+#[derive(Debug, Clone, Default)]
+pub struct SignOptions {
+    /// Part names to sign, every part except the signature parts when empty.
+    ///
+    /// The relationships of the package and of every signed part are signed too.
+    pub parts: Vec<String>,
+    /// Relationship types that are left out of the signed relationships, e.g. types of
+    /// relationships that are expected to change after signing.
+    pub unsigned_relationship_types: Vec<String>,
+    /// Signing time recorded in the signature, the current time when `None`.
+    pub time: Option<DateTime<Utc>>,
+}
+
+/// Build the XML signature of the selected parts and relationships of `package`.
+pub(crate) fn sign(
+    package: &OpenXmlPackage,
+    key: &SigningKey,
+    options: &SignOptions,
+) -> Result<Vec<u8>, OoxmlError> {
+    let algorithm = DigestAlgorithm::Sha256;
+    let parts: Vec<&OpenXmlPart> = if options.parts.is_empty() {
+        package
+            .parts()
+            .filter(|part| !is_signature_part(part))
+            .collect()
+    } else {
+        options
+            .parts
+            .iter()
+            .map(|name| {
+                package
+                    .get_part(name)
+                    .ok_or_else(|| OoxmlError::PartNotFound(name.clone()))
+            })
+            .collect::<Result<_, _>>()?
+    };
+
+    let mut manifest = XmlElement::new("Manifest");
+    let sources = std::iter::once(PackUri::root()).chain(parts.iter().map(|part| part.name()));
+    for source in sources {
+        let relationships = match package.relationships_of(source.as_str()) {
+            Some(relationships) => relationships,
+            None => continue,
+        };
+        let source_ids: Vec<&str> = relationships
+            .iter()
+            .filter(|r| {
+                r.relationship_type() != DIGITAL_SIGNATURE_ORIGIN_RELATIONSHIP_TYPE
+                    && !options
+                        .unsigned_relationship_types
+                        .iter()
+                        .any(|t| t == r.relationship_type())
+            })
+            .map(|r| r.id())
+            .collect();
+        if source_ids.is_empty() {
+            continue;
+        }
+
+        let uri = format!(
+            "{}?ContentType={}",
+            source.relationships_part(),
+            RELATIONSHIPS_CONTENT_TYPE
+        );
+        let reference = manifest.push_child(XmlElement::new("Reference"));
+        reference.set_attribute("URI", uri);
+        let transforms = reference.push_child(XmlElement::new("Transforms"));
+        let transform = transforms.push_child(XmlElement::new("Transform"));
+        transform.set_attribute("Algorithm", RELATIONSHIP_TRANSFORM_ALGORITHM);
+        for id in &source_ids {
+            let relationship_reference =
+                transform.push_child(XmlElement::new("mdssi:RelationshipReference"));
+            relationship_reference.set_attribute("xmlns:mdssi", PACKAGE_SIGNATURE_NAMESPACE_URI);
+            relationship_reference.set_attribute("SourceId", *id);
+        }
+        transforms
+            .push_child(XmlElement::new("Transform"))
+            .set_attribute("Algorithm", C14N_ALGORITHM);
+        push_digest(
+            reference,
+            algorithm,
+            &relationship_transform(relationships, &source_ids, &[]),
+        );
+    }
+    for part in &parts {
+        let content_type = part
+            .content_type()
+            .ok_or_else(|| OoxmlError::InvalidSignature {
+                part: part.name().to_string(),
+                reason: "a part without content type can not be signed".into(),
+            })?;
+        let reference = manifest.push_child(XmlElement::new("Reference"));
+        reference.set_attribute(
+            "URI",
+            format!("{}?ContentType={}", part.name(), content_type),
+        );
+        push_digest(reference, algorithm, part.data()?);
+    }
+
+    let time = options.time.unwrap_or_else(Utc::now);
+    let mut object = XmlElement::new("Object");
+    object.set_attribute("Id", "idPackageObject");
+    object.push_child(manifest);
+    let property = object
+        .push_child(XmlElement::new("SignatureProperties"))
+        .push_child(XmlElement::new("SignatureProperty"));
+    property.set_attribute("Id", "idSignatureTime");
+    property.set_attribute("Target", "#idPackageSignature");
+    let signature_time = property.push_child(XmlElement::new("mdssi:SignatureTime"));
+    signature_time.set_attribute("xmlns:mdssi", PACKAGE_SIGNATURE_NAMESPACE_URI);
+    signature_time
+        .push_child(XmlElement::new("mdssi:Format"))
+        .push_text("YYYY-MM-DDThh:mm:ssTZD");
+    signature_time
+        .push_child(XmlElement::new("mdssi:Value"))
+        .push_text(time.format("%Y-%m-%dT%H:%M:%SZ").to_string());
+
+    let mut signature = XmlElement::new("Signature");
+    signature.set_attribute("xmlns", XMLDSIG_NAMESPACE_URI);
+    signature.set_attribute("Id", "idPackageSignature");
+    let scope = signature.scope(&NamespaceScope::new());
+
+    let mut signed_info = XmlElement::new("SignedInfo");
+    signed_info
+        .push_child(XmlElement::new("CanonicalizationMethod"))
+        .set_attribute("Algorithm", C14N_ALGORITHM);
+    signed_info
+        .push_child(XmlElement::new("SignatureMethod"))
+        .set_attribute("Algorithm", algorithm.signature_method());
+    let reference = signed_info.push_child(XmlElement::new("Reference"));
+    reference.set_attribute("Type", format!("{}Object", XMLDSIG_NAMESPACE_URI));
+    reference.set_attribute("URI", "#idPackageObject");
+    push_digest(
+        reference,
+        algorithm,
+        &object.canonicalize(C14nMode::Inclusive, &scope, &XmlAttributes::new()),
+    );
+
+    let canonical = signed_info.canonicalize(C14nMode::Inclusive, &scope, &XmlAttributes::new());
+    let signature_value = key
+        .private_key
+        .sign(algorithm.pkcs1v15(), &algorithm.digest(&canonical))
+        .map_err(|e| invalid("private key", e))?;
+
+    signature.push_child(signed_info);
+    signature
+        .push_child(XmlElement::new("SignatureValue"))
+        .push_text(BASE64.encode(signature_value));
+    signature
+        .push_child(XmlElement::new("KeyInfo"))
+        .push_child(XmlElement::new("X509Data"))
+        .push_child(XmlElement::new("X509Certificate"))
+        .push_text(BASE64.encode(key.certificate.der()));
+    signature.push_child(object);
+    Ok(signature.to_xml().into_bytes())
+}
+
+/// Whether `part` belongs to the signatures of the package.
+fn is_signature_part(part: &OpenXmlPart) -> bool {
+    let content_type = match part.content_type() {
+        Some(content_type) => content_type,
+        None => return false,
+    };
+    [
+        DIGITAL_SIGNATURE_ORIGIN_CONTENT_TYPE,
+        DIGITAL_SIGNATURE_CONTENT_TYPE,
+        DIGITAL_SIGNATURE_CERTIFICATE_CONTENT_TYPE,
+    ]
+    .iter()
+    .any(|signature_type| content_type_eq(content_type, signature_type))
+}
+
+fn push_digest(reference: &mut XmlElement, algorithm: DigestAlgorithm, data: &[u8]) {
+    reference
+        .push_child(XmlElement::new("DigestMethod"))
+        .set_attribute("Algorithm", algorithm.digest_method());
+    reference
+        .push_child(XmlElement::new("DigestValue"))
+        .push_text(BASE64.encode(algorithm.digest(data)));
+}
+
+/// Result of checking one signed reference.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReferenceStatus {
+    Valid,
+    /// The referenced content was modified after signing.
+    DigestMismatch,
+    /// The content type in the reference URI differs from the part's content type.
+    ContentTypeMismatch,
+    /// The referenced part or element is missing.
+    NotFound,
+    /// The reference uses an algorithm or transform that is not supported.
+    Unsupported(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceVerification {
+    uri: String,
+    status: ReferenceStatus,
+}
+
+impl ReferenceVerification {
+    /// The reference URI, e.g. `/xl/workbook.xml?ContentType=...` or `#idPackageObject`.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    pub fn status(&self) -> &ReferenceStatus {
+        &self.status
+    }
+}
+
+/// Result of verifying a [`PackageSignature`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignatureVerification {
+    signature_value_valid: bool,
+    references: Vec<ReferenceVerification>,
+}
+
+impl SignatureVerification {
+    /// Whether the signature value matches `SignedInfo` and every reference is intact.
+    pub fn is_valid(&self) -> bool {
+        self.signature_value_valid
+            && self
+                .references
+                .iter()
+                .all(|reference| reference.status == ReferenceStatus::Valid)
+    }
+
+    /// Whether `SignedInfo` was signed by the key of the signer certificate.
+    pub fn signature_value_valid(&self) -> bool {
+        self.signature_value_valid
+    }
+
+    /// The `SignedInfo` references followed by the references of the package manifest and
+    /// of any manifest `SignedInfo` does not cover.
+    pub fn references(&self) -> &[ReferenceVerification] {
+        &self.references
+    }
+}
+
+/// An XML digital signature part of a package.
+#[derive(Debug, Clone)]
+pub struct PackageSignature {
+    part: PackUri,
+    signature: XmlElement,
+    signer: Option<SignerCertificate>,
+}
+
+impl PackageSignature {
+    /// Read every signature part of `package`.
+    pub(crate) fn read_all(package: &OpenXmlPackage) -> Result<Vec<Self>, OoxmlError> {
+        let mut signatures = Vec::new();
+        let origins = package
+            .relationships()
+            .get_relationships_by_type(DIGITAL_SIGNATURE_ORIGIN_RELATIONSHIP_TYPE)
+            .filter_map(|relationship| relationship.target_part(&PackUri::root()));
+        for origin in origins {
+            for part in package.related_parts(origin.as_str(), DIGITAL_SIGNATURE_RELATIONSHIP_TYPE)
+            {
+                signatures.push(Self::read(package, part.name(), part.data()?)?);
+            }
+        }
+        Ok(signatures)
+    }
+
+    fn read(package: &OpenXmlPackage, part: PackUri, xml: &[u8]) -> Result<Self, OoxmlError> {
+        let signature = XmlElement::parse(xml)?;
+        if signature.local_name() != "Signature" || signature.child("SignedInfo").is_none() {
+            return Err(OoxmlError::InvalidSignature {
+                part: part.to_string(),
+                reason: "not an XML signature".into(),
+            });
+        }
+
+        let key_info_certificate = signature
+            .child("KeyInfo")
+            .and_then(|key_info| key_info.find("X509Certificate"))
+            .map(|certificate| decode_base64(&certificate.text()))
+            .transpose()
+            .map_err(|e| e.in_part(part.as_str()))?;
+        let certificate = match key_info_certificate {
+            Some(der) => Some(der),
+            None => package
+                .related_parts(
+                    part.as_str(),
+                    DIGITAL_SIGNATURE_CERTIFICATE_RELATIONSHIP_TYPE,
+                )
+                .first()
+                .map(|certificate| certificate.data().map(<[u8]>::to_vec))
+                .transpose()?,
+        };
+        let signer = certificate
+            .map(|der| SignerCertificate::from_der(&der))
+            .transpose()
+            .map_err(|e| e.in_part(part.as_str()))?;
+
+        Ok(Self {
+            part,
+            signature,
+            signer,
+        })
+    }
+
+    /// The signature part name, e.g. `/_xmlsignatures/sig1.xml`.
+    pub fn part_name(&self) -> &str {
+        self.part.as_str()
+    }
+
+    /// The certificate embedded in `KeyInfo` or stored in a certificate part.
+    pub fn signer(&self) -> Option<&SignerCertificate> {
+        self.signer.as_ref()
+    }
+
+    /// The signing time recorded in the package signature properties, as written by the
+    /// signer, e.g. `2021-03-01T10:00:00Z`.
+    pub fn signature_time(&self) -> Option<String> {
+        self.signature
+            .find("SignatureTime")
+            .and_then(|time| time.child("Value"))
+            .map(XmlElement::text)
+    }
+
+    /// Part names referenced by the package manifest, relationships parts included.
+    ///
+    /// Only manifests covered by an intact `SignedInfo` reference are signed.
+    pub fn signed_parts(&self) -> Vec<String> {
+        let (_, manifests) = self.verify_object_references();
+        manifests
+            .into_iter()
+            .flat_map(|manifest| manifest.children_named("Reference"))
+            .filter_map(|reference| reference.attribute("URI"))
+            .map(|uri| PackUri::new(uri).to_string())
+            .collect()
+    }
+
+    /// Verify the `SignedInfo` references and collect the manifests of the elements that
+    /// passed their digest check.
+    fn verify_object_references(&self) -> (Vec<ReferenceVerification>, Vec<&XmlElement>) {
+        let signed_info = self.signature.child("SignedInfo").expect("checked on read");
+        let mut references = Vec::new();
+        let mut manifests = Vec::new();
+        for reference in signed_info.children_named("Reference") {
+            let (verification, element) = self.verify_object_reference(reference);
+            if let Some(element) = element.filter(|_| verification.status == ReferenceStatus::Valid)
+            {
+                if element.local_name() == "Manifest" {
+                    manifests.push(element);
+                } else {
+                    manifests.extend(element.children_named("Manifest"));
+                }
+            }
+            references.push(verification);
+        }
+        (references, manifests)
+    }
+
+    /// Verify the signature value and the digests of every signed reference against the
+    /// current content of `package`.
+    ///
+    /// References of manifests that no intact `SignedInfo` reference covers are reported
+    /// as [`ReferenceStatus::Unsupported`].
+    pub fn verify(&self, package: &OpenXmlPackage) -> Result<SignatureVerification, OoxmlError> {
+        let signed_info = self.signature.child("SignedInfo").expect("checked on read");
+        let (mut references, manifests) = self.verify_object_references();
+        for manifest in &manifests {
+            references.extend(
+                manifest
+                    .children_named("Reference")
+                    .map(|reference| verify_part_reference(reference, package)),
+            );
+        }
+        let unsigned = self
+            .signature
+            .children_named("Object")
+            .flat_map(|object| object.children_named("Manifest"))
+            .filter(|manifest| {
+                !manifests
+                    .iter()
+                    .any(|signed| std::ptr::eq(*signed, *manifest))
+            })
+            .flat_map(|manifest| manifest.children_named("Reference"));
+        references.extend(unsigned.map(|reference| ReferenceVerification {
+            uri: reference.attribute("URI").unwrap_or_default().to_string(),
+            status: ReferenceStatus::Unsupported(
+                "manifest not covered by a SignedInfo reference".into(),
+            ),
+        }));
+
+        let signature_value_valid = self
+            .verify_signature_value(signed_info)
+            .map_err(|e| e.in_part(self.part.as_str()))?;
+        Ok(SignatureVerification {
+            signature_value_valid,
+            references,
+        })
+    }
+
+    fn verify_signature_value(&self, signed_info: &XmlElement) -> Result<bool, OoxmlError> {
+        let signer = match &self.signer {
+            Some(signer) => signer,
+            None => return Ok(false),
+        };
+        let canonicalization = signed_info
+            .child("CanonicalizationMethod")
+            .and_then(|method| method.attribute("Algorithm"))
+            .unwrap_or_default();
+        let mode = c14n_mode(canonicalization)
+            .ok_or_else(|| invalid("canonicalization method", canonicalization))?;
+        let method = signed_info
+            .child("SignatureMethod")
+            .and_then(|method| method.attribute("Algorithm"))
+            .unwrap_or_default();
+        let algorithm = DigestAlgorithm::from_signature_method(method)
+            .ok_or_else(|| invalid("signature method", method))?;
+        let signature_value = self
+            .signature
+            .child("SignatureValue")
+            .map(|value| decode_base64(&value.text()))
+            .transpose()?
+            .unwrap_or_default();
+
+        let scope = self.signature.scope(&NamespaceScope::new());
+        let xml_attributes = self.signature.xml_attributes(&XmlAttributes::new());
+        let canonical = signed_info.canonicalize(mode, &scope, &xml_attributes);
+        let hashed = algorithm.digest(&canonical);
+        Ok(signer
+            .public_key()?
+            .verify(algorithm.pkcs1v15(), &hashed, &signature_value)
+            .is_ok())
+    }
+
+    /// Verify a `SignedInfo` reference to an element of the signature, e.g. the package
+    /// object, returning the referenced element if it was found.
+    fn verify_object_reference(
+        &self,
+        reference: &XmlElement,
+    ) -> (ReferenceVerification, Option<&XmlElement>) {
+        let uri = reference.attribute("URI").unwrap_or_default();
+        let (status, element) = match uri.strip_prefix('#') {
+            None => (
+                ReferenceStatus::Unsupported(format!("reference URI {}", uri)),
+                None,
+            ),
+            Some(id) => match self.signature.find_by_id(id) {
+                None => (ReferenceStatus::NotFound, None),
+                Some((element, scope, xml_attributes)) => {
+                    let mut mode = Some(C14nMode::Inclusive);
+                    for transform in transforms(reference) {
+                        mode = c14n_mode(transform.attribute("Algorithm").unwrap_or_default());
+                    }
+                    let status = match mode {
+                        Some(mode) => check_digest(
+                            reference,
+                            &element.canonicalize(mode, &scope, &xml_attributes),
+                        ),
+                        None => ReferenceStatus::Unsupported("transform".into()),
+                    };
+                    (status, Some(element))
+                }
+            },
+        };
+        let verification = ReferenceVerification {
+            uri: uri.to_string(),
+            status,
+        };
+        (verification, element)
+    }
+}
+
+/// Verify a package manifest reference to a part, e.g.
+/// `/xl/workbook.xml?ContentType=application/vnd...sheet.main+xml`.
+fn verify_part_reference(
+    reference: &XmlElement,
+    package: &OpenXmlPackage,
+) -> ReferenceVerification {
+    let uri = reference.attribute("URI").unwrap_or_default();
+    let part_name = PackUri::new(uri);
+    let content_type = uri
+        .split_once('?')
+        .and_then(|(_, query)| query.strip_prefix("ContentType="))
+        .unwrap_or_default();
+    let transforms: Vec<&str> = transforms(reference)
+        .map(|transform| transform.attribute("Algorithm").unwrap_or_default())
+        .collect();
+
+    let status = if let Some(source) = part_name.source_part() {
+        let relationships = package.relationships_of(source.as_str());
+        let transform = self::transforms(reference)
+            .find(|t| t.attribute("Algorithm") == Some(RELATIONSHIP_TRANSFORM_ALGORITHM));
+        match (relationships, transform) {
+            (None, _) => ReferenceStatus::NotFound,
+            (_, None) => ReferenceStatus::Unsupported(
+                "relationships part reference without relationship transform".into(),
+            ),
+            _ if !content_type.eq_ignore_ascii_case(RELATIONSHIPS_CONTENT_TYPE) => {
+                ReferenceStatus::ContentTypeMismatch
+            }
+            (Some(relationships), Some(transform)) => {
+                let source_ids: Vec<&str> = transform
+                    .children_named("RelationshipReference")
+                    .filter_map(|r| r.attribute("SourceId"))
+                    .collect();
+                let source_types: Vec<&str> = transform
+                    .children_named("RelationshipsGroupReference")
+                    .filter_map(|r| r.attribute("SourceType"))
+                    .collect();
+                let transformed = relationship_transform(relationships, &source_ids, &source_types);
+                check_digest(reference, &transformed)
+            }
+        }
+    } else {
+        match package.get_part(part_name.as_str()) {
+            None => ReferenceStatus::NotFound,
+            Some(part) => {
+                let part_content_type = part.content_type().map(|ct| ct.as_str());
+                if !part_content_type
+                    .unwrap_or_default()
+                    .eq_ignore_ascii_case(content_type)
+                {
+                    ReferenceStatus::ContentTypeMismatch
+                } else {
+                    match (part.data(), transforms.as_slice()) {
+                        (Err(e), _) => ReferenceStatus::Unsupported(e.to_string()),
+                        (Ok(data), []) => check_digest(reference, data),
+                        (Ok(data), [algorithm]) => match c14n_mode(algorithm) {
+                            Some(mode) => match XmlElement::parse(data) {
+                                Ok(root) => check_digest(
+                                    reference,
+                                    &root.canonicalize(
+                                        mode,
+                                        &NamespaceScope::new(),
+                                        &XmlAttributes::new(),
+                                    ),
+                                ),
+                                Err(_) => ReferenceStatus::DigestMismatch,
+                            },
+                            None => ReferenceStatus::Unsupported(algorithm.to_string()),
+                        },
+                        (Ok(_), _) => ReferenceStatus::Unsupported("transform chain".into()),
+                    }
+                }
+            }
+        }
+    };
+    ReferenceVerification {
+        uri: uri.to_string(),
+        status,
+    }
+}
+
+/// Apply the OPC relationship transform (ECMA-376 Part 2, 13.2.4.24) followed by
+/// canonicalization: keep the selected relationships, sorted by `Id`, with an explicit
+/// `TargetMode`.
+fn relationship_transform(
+    relationships: &Relationships,
+    source_ids: &[&str],
+    source_types: &[&str],
+) -> Vec<u8> {
+    let mut selected: Vec<_> = relationships
+        .iter()
+        .filter(|r| source_ids.contains(&r.id()) || source_types.contains(&r.relationship_type()))
+        .collect();
+    selected.sort_by(|a, b| a.id().cmp(b.id()));
+
+    let mut root = XmlElement::new("Relationships");
+    root.set_attribute("xmlns", RELATIONSHIP_NAMESPACE_URI);
+    for relationship in selected {
+        let element = root.push_child(XmlElement::new("Relationship"));
+        element.set_attribute("Id", relationship.id());
+        element.set_attribute("Target", relationship.target());
+        element.set_attribute("TargetMode", relationship.target_mode().as_str());
+        element.set_attribute("Type", relationship.relationship_type());
+    }
+    root.canonicalize(
+        C14nMode::Inclusive,
+        &NamespaceScope::new(),
+        &XmlAttributes::new(),
+    )
+}
+
+fn transforms(reference: &XmlElement) -> impl Iterator<Item = &XmlElement> {
+    reference
+        .children_named("Transforms")
+        .flat_map(|transforms| transforms.children_named("Transform"))
+}
+
+fn check_digest(reference: &XmlElement, data: &[u8]) -> ReferenceStatus {
+    let method = reference
+        .child("DigestMethod")
+        .and_then(|method| method.attribute("Algorithm"))
+        .unwrap_or_default();
+    let algorithm = match DigestAlgorithm::from_digest_method(method) {
+        Some(algorithm) => algorithm,
+        None => return ReferenceStatus::Unsupported(format!("digest method {}", method)),
+    };
+    let expected = reference
+        .child("DigestValue")
+        .and_then(|value| decode_base64(&value.text()).ok());
+    if expected == Some(algorithm.digest(data)) {
+        ReferenceStatus::Valid
+    } else {
+        ReferenceStatus::DigestMismatch
+    }
+}
+
+fn c14n_mode(algorithm: &str) -> Option<C14nMode> {
+    match algorithm {
+        C14N_ALGORITHM => Some(C14nMode::Inclusive),
+        EXCLUSIVE_C14N_ALGORITHM => Some(C14nMode::Exclusive),
+        _ => None,
+    }
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, OoxmlError> {
+    let text: String = text.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    BASE64.decode(text).map_err(|e| invalid("base64 value", e))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+fn invalid<E: std::fmt::Display>(what: &str, e: E) -> OoxmlError {
+    OoxmlError::InvalidSignature {
+        part: String::new(),
+        reason: format!("{}: {}", what, e),
+    }
+}
@@ -0,0 +1,403 @@
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+
+const RELATIONSHIPS_DIR: &str = "_rels";
+const RELATIONSHIPS_EXTENSION: &str = ".rels";
+
+/// Pack URI of a part, as defined in OPC (ECMA-376 Part 2, 9.1).
+///
+/// The part name always starts with `/`, has dot segments removed and percent-encoded
+/// octets normalized. Part names are compared case-insensitively after decoding
+/// percent-encoded non-ASCII characters, so `/xl/Workbook.xml` and `/xl/workbook.xml`
+/// refer to the same part. Encoded ASCII characters, e.g. `%2F`, are never decoded.
+#[derive(Debug, Clone)]
+pub struct PackUri {
+    name: String,
+    key: String,
+}
+
+impl PackUri {
+    /// Create a pack URI from a part name or a zip item name.
+    pub fn new<S: AsRef<str>>(name: S) -> Self {
+        let name = strip_fragment(name.as_ref());
+        let segments = remove_dot_segments(name.split('/'));
+        Self::from_segments(&segments)
+    }
+
+    /// The package root, source of the package relationships.
+    pub fn root() -> Self {
+        Self {
+            name: "/".into(),
+            key: "/".into(),
+        }
+    }
+
+    fn from_segments(segments: &[&str]) -> Self {
+        let mut name = String::with_capacity(segments.iter().map(|s| s.len() + 1).sum());
+        for segment in segments {
+            name.push('/');
+            name.push_str(&normalize_percent_encoding(segment));
+        }
+        if name.is_empty() {
+            return Self::root();
+        }
+        let key = comparison_key(&name);
+        Self { name, key }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.name
+    }
+
+    /// The zip item name of the part, i.e. the part name without the leading `/`.
+    pub fn zip_item_name(&self) -> &str {
+        &self.name[1..]
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.name == "/"
+    }
+
+    /// The last segment of the part name.
+    pub fn file_name(&self) -> &str {
+        self.name.rsplit('/').next().unwrap_or_default()
+    }
+
+    /// The extension of the part name, without the leading `.`.
+    pub fn extension(&self) -> Option<&str> {
+        let file_name = self.file_name();
+        file_name
+            .rfind('.')
+            .map(|idx| &file_name[idx + 1..])
+            .filter(|ext| !ext.is_empty())
+    }
+
+    /// Resolve a relationship target against this part (RFC 3986, 5.2).
+    ///
+    /// Relative targets are resolved against the part's directory, absolute targets
+    /// against the package root. Fragments and queries are dropped.
+    pub fn resolve(&self, target: &str) -> Self {
+        let target = strip_fragment(target);
+        if target.starts_with('/') {
+            return Self::new(target);
+        }
+        let base = match self.name.rfind('/') {
+            Some(idx) => &self.name[..idx],
+            None => "",
+        };
+        let segments = remove_dot_segments(base.split('/').chain(target.split('/')));
+        Self::from_segments(&segments)
+    }
+
+    /// Get the relationship target of `part` relative to this part.
+    pub fn relative_target(&self, part: &PackUri) -> String {
+        let base: Vec<&str> = self.name[1..].split('/').collect();
+        let target: Vec<&str> = part.name[1..].split('/').collect();
+        let base_dir = &base[..base.len() - 1];
+        let common = base_dir
+            .iter()
+            .zip(&target)
+            .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+            .count();
+        let mut segments = vec![".."; base_dir.len() - common];
+        segments.extend(&target[common..]);
+        segments.join("/")
+    }
+
+    /// Get the relationships part name of this part, e.g. `/xl/_rels/workbook.xml.rels`
+    /// for `/xl/workbook.xml` and `/_rels/.rels` for the package root.
+    pub fn relationships_part(&self) -> Self {
+        let idx = self.name.rfind('/').unwrap_or_default();
+        let file_name = if self.is_root() {
+            ""
+        } else {
+            &self.name[idx + 1..]
+        };
+        Self::new(format!(
+            "{}/{}/{}{}",
+            &self.name[..idx],
+            RELATIONSHIPS_DIR,
+            file_name,
+            RELATIONSHIPS_EXTENSION
+        ))
+    }
+
+    pub fn is_relationships_part(&self) -> bool {
+        self.source_part().is_some()
+    }
+
+    /// Get the source part of a relationships part, the reverse of
+    /// [`PackUri::relationships_part`]. Returns `None` if this is not a relationships part.
+    pub fn source_part(&self) -> Option<Self> {
+        let (dir, file_name) = self.name.rsplit_once('/')?;
+        let (parent, rels_dir) = dir.rsplit_once('/')?;
+        if !rels_dir.eq_ignore_ascii_case(RELATIONSHIPS_DIR) {
+            return None;
+        }
+        let extension_idx = file_name.len().checked_sub(RELATIONSHIPS_EXTENSION.len())?;
+        if !file_name.is_char_boundary(extension_idx)
+            || !file_name[extension_idx..].eq_ignore_ascii_case(RELATIONSHIPS_EXTENSION)
+        {
+            return None;
+        }
+        let source = &file_name[..extension_idx];
+        if source.is_empty() {
+            return if parent.is_empty() {
+                Some(Self::root())
+            } else {
+                None
+            };
+        }
+        Some(Self::new(format!("{}/{}", parent, source)))
+    }
+}
+
+/// Split the zip item name of a piece, e.g. `xl/worksheets/sheet1.xml/[1].last.piece`,
+/// into the zip item name of its part, the piece number and whether it is the last piece.
+pub(crate) fn piece(zip_item_name: &str) -> Option<(&str, u32, bool)> {
+    let (part, piece) = zip_item_name.rsplit_once('/')?;
+    let piece = piece.to_ascii_lowercase();
+    let (number, suffix) = piece.strip_prefix('[')?.split_once(']')?;
+    if number.is_empty() || !number.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let number = number.parse().ok()?;
+    match suffix {
+        ".piece" => Some((part, number, false)),
+        ".last.piece" => Some((part, number, true)),
+        _ => None,
+    }
+}
+
+impl Default for PackUri {
+    fn default() -> Self {
+        Self::root()
+    }
+}
+
+impl PartialEq for PackUri {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for PackUri {}
+
+impl Hash for PackUri {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key.hash(state)
+    }
+}
+
+impl Display for PackUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl From<&str> for PackUri {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for PackUri {
+    fn from(name: String) -> Self {
+        Self::new(name)
+    }
+}
+
+impl AsRef<str> for PackUri {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+/// Check a part name against the OPC part name grammar (ECMA-376 Part 2, 6.2.2.2),
+/// returning the violated rule.
+pub fn validate_part_name(name: &str) -> Result<(), &'static str> {
+    let name = name
+        .strip_prefix('/')
+        .ok_or("part name must start with a forward slash")?;
+    if name.ends_with('/') {
+        return Err("part name must not end with a forward slash");
+    }
+    for segment in name.split('/') {
+        if segment.is_empty() {
+            return Err("part name segments must not be empty");
+        }
+        if segment.ends_with('.') {
+            return Err("part name segments must not end with a dot");
+        }
+        let bytes = segment.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'%' => {
+                    let byte = segment
+                        .get(i + 1..i + 3)
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                        .ok_or("part name has an invalid percent-encoding")?;
+                    if byte == b'/' || byte == b'\\' {
+                        return Err("part name segments must not percent-encode slashes");
+                    }
+                    if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                        return Err("part name must not percent-encode unreserved characters");
+                    }
+                    i += 3;
+                    continue;
+                }
+                byte if byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@".contains(&byte) => {}
+                byte if !byte.is_ascii() => {}
+                _ => return Err("part name has a character that must be percent-encoded"),
+            }
+            i += 1;
+        }
+    }
+    Ok(())
+}
+
+fn strip_fragment(uri: &str) -> &str {
+    uri.split(['#', '?']).next().unwrap_or_default()
+}
+
+fn remove_dot_segments<'a, I: Iterator<Item = &'a str>>(segments: I) -> Vec<&'a str> {
+    let mut output = Vec::new();
+    for segment in segments {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                output.pop();
+            }
+            segment => output.push(segment),
+        }
+    }
+    output
+}
+
+/// The case folded part name with percent-encoded UTF-8 sequences decoded.
+///
+/// Unreserved ASCII characters are already decoded by [`normalize_percent_encoding`], the
+/// remaining encoded ASCII characters are reserved and stay encoded, so `/a%2Fb` never
+/// equals `/a/b`.
+fn comparison_key(name: &str) -> String {
+    let bytes = name.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok())
+            .filter(|byte| !byte.is_ascii());
+        match escape {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded)
+        .unwrap_or_else(|_| name.to_string())
+        .to_lowercase()
+}
+
+/// Decode percent-encoded unreserved characters and upper-case the remaining escapes
+/// (RFC 3986, 6.2.2).
+fn normalize_percent_encoding(segment: &str) -> String {
+    if !segment.contains('%') {
+        return segment.to_string();
+    }
+    let bytes = segment.as_bytes();
+    let mut normalized = String::with_capacity(segment.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| bytes[i] == b'%' && hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escape {
+            Some(byte) if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) => {
+                normalized.push(byte as char);
+                i += 3;
+            }
+            Some(byte) => {
+                normalized.push_str(&format!("%{:02X}", byte));
+                i += 3;
+            }
+            None => {
+                let ch = segment[i..].chars().next().unwrap_or_default();
+                normalized.push(ch);
+                i += ch.len_utf8();
+            }
+        }
+    }
+    normalized
+}
+
+#[test]
+fn part_name_validation() {
+    assert!(validate_part_name("/xl/workbook.xml").is_ok());
+    assert!(validate_part_name("/xl/media/image%20one.png").is_ok());
+    assert!(validate_part_name("xl/workbook.xml").is_err());
+    assert!(validate_part_name("/xl//workbook.xml").is_err());
+    assert!(validate_part_name("/xl/workbook.").is_err());
+    assert!(validate_part_name("/xl/a%2Fb.xml").is_err());
+    assert!(validate_part_name("/xl/%41.xml").is_err());
+    assert!(validate_part_name("/xl/a b.xml").is_err());
+}
+
+#[test]
+fn pack_uri_resolution() {
+    let sheet = PackUri::new("xl/worksheets/sheet1.xml");
+    assert_eq!(sheet.as_str(), "/xl/worksheets/sheet1.xml");
+    assert_eq!(
+        sheet.resolve("../drawings/drawing1.xml").as_str(),
+        "/xl/drawings/drawing1.xml"
+    );
+    assert_eq!(
+        sheet.resolve("/xl/media/image%31.png#frag").as_str(),
+        "/xl/media/image1.png"
+    );
+    assert_eq!(
+        PackUri::root().resolve("xl/workbook.xml"),
+        sheet.resolve("../Workbook.xml")
+    );
+    assert_eq!(
+        PackUri::new("/xl/a%2fb%c3%a4.xml").as_str(),
+        "/xl/a%2Fb%C3%A4.xml"
+    );
+    assert_eq!(PackUri::new("/xl/b%C3%A4.xml"), PackUri::new("/xl/bä.xml"));
+    assert_eq!(PackUri::new("/xl/BÄ.xml"), PackUri::new("/xl/b%c3%a4.xml"));
+    assert_ne!(PackUri::new("/xl/a%2Fb.xml"), PackUri::new("/xl/a/b.xml"));
+    assert_ne!(PackUri::new("/xl/a%5Cb.xml"), PackUri::new("/xl/a\\b.xml"));
+    assert_eq!(
+        sheet.relationships_part().as_str(),
+        "/xl/worksheets/_rels/sheet1.xml.rels"
+    );
+    assert_eq!(
+        PackUri::root().relationships_part().as_str(),
+        "/_rels/.rels"
+    );
+    assert_eq!(
+        sheet.relationships_part().source_part(),
+        Some(sheet.clone())
+    );
+    assert_eq!(
+        PackUri::new("/_rels/.rels").source_part(),
+        Some(PackUri::root())
+    );
+    assert_eq!(sheet.source_part(), None);
+    assert_eq!(
+        PackUri::new("/xl/workbook.xml").relative_target(&sheet),
+        "worksheets/sheet1.xml"
+    );
+    assert_eq!(
+        sheet.relative_target(&PackUri::new("/xl/drawings/drawing1.xml")),
+        "../drawings/drawing1.xml"
+    );
+}
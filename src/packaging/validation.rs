@@ -0,0 +1,65 @@
+use std::fmt::Display;
+
+/// Kind of an OPC conformance problem found by
+/// [`OpenXmlPackage::validate`](super::package::OpenXmlPackage::validate).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticKind {
+    /// A part has neither a content type override nor a default for its extension.
+    MissingContentType,
+    /// A content type override names a part that is not in the package.
+    OverrideWithoutPart,
+    /// A relationships part uses the same `Id` more than once.
+    DuplicateRelationshipId,
+    /// An internal relationship targets a part that is not in the package.
+    DanglingRelationship,
+    /// A part name does not follow the part name grammar.
+    IllegalPartName,
+    /// Two zip items map to the same part name when compared case-insensitively.
+    DuplicatePartName,
+    /// The package has no `officeDocument` relationship to an existing part.
+    MissingMainDocument,
+}
+
+/// A single conformance problem of a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    kind: DiagnosticKind,
+    part: Option<String>,
+    message: String,
+}
+
+impl Diagnostic {
+    pub(crate) fn new<S: Into<String>>(
+        kind: DiagnosticKind,
+        part: Option<&str>,
+        message: S,
+    ) -> Self {
+        Self {
+            kind,
+            part: part.map(String::from),
+            message: message.into(),
+        }
+    }
+
+    pub fn kind(&self) -> DiagnosticKind {
+        self.kind
+    }
+
+    /// The part the problem was found in, `None` for package level problems.
+    pub fn part(&self) -> Option<&str> {
+        self.part.as_deref()
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.part {
+            Some(part) => write!(f, "{:?} in {}: {}", self.kind, part, self.message),
+            None => write!(f, "{:?}: {}", self.kind, self.message),
+        }
+    }
+}
@@ -1,15 +1,32 @@
-use serde::{Deserialize, Serialize};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::SecondsFormat;
+use serde::{Deserialize, Deserializer, Serialize};
 
+use crate::error::OoxmlError;
+use crate::packaging::property::{parse_w3cdtf, DateTime};
+
+/// A value of the `docPropsVTypes` schema (ECMA-376 Part 1, 22.4), used by the
+/// extended and custom properties.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename = "vt:variant")]
-
 pub enum Variant {
     #[serde(rename = "vt:vector")]
     VtVector {
         size: usize,
         #[serde(rename = "baseType")]
         base_type: String,
-        #[serde(rename = "$value")]
+        #[serde(rename = "$value", default)]
+        variants: Vec<Variant>,
+    },
+    #[serde(rename = "vt:array")]
+    VtArray {
+        #[serde(rename = "lBounds")]
+        lower_bounds: i32,
+        #[serde(rename = "uBounds")]
+        upper_bounds: i32,
+        #[serde(rename = "baseType")]
+        base_type: String,
+        #[serde(rename = "$value", default)]
         variants: Vec<Variant>,
     },
     #[serde(rename = "vt:variant")]
@@ -17,20 +34,70 @@ pub enum Variant {
         #[serde(rename = "$value")]
         value: Box<Variant>,
     },
+    #[serde(rename = "vt:blob", deserialize_with = "deserialize_binary")]
+    VtBlob(Vec<u8>),
+    #[serde(rename = "vt:oblob", deserialize_with = "deserialize_binary")]
+    VtOblob(Vec<u8>),
+    #[serde(rename = "vt:stream", deserialize_with = "deserialize_binary")]
+    VtStream(Vec<u8>),
+    #[serde(rename = "vt:ostream", deserialize_with = "deserialize_binary")]
+    VtOstream(Vec<u8>),
+    #[serde(rename = "vt:storage", deserialize_with = "deserialize_binary")]
+    VtStorage(Vec<u8>),
+    #[serde(rename = "vt:ostorage", deserialize_with = "deserialize_binary")]
+    VtOstorage(Vec<u8>),
+    #[serde(rename = "vt:empty")]
+    VtEmpty,
     #[serde(rename = "vt:null")]
     VtNull,
     #[serde(rename = "vt:i1")]
     VtI1(i8),
     #[serde(rename = "vt:i2")]
-    VtI2(i8),
+    VtI2(i16),
     #[serde(rename = "vt:i4")]
-    VtI4(i8),
+    VtI4(i32),
     #[serde(rename = "vt:i8")]
-    VtI8(i8),
+    VtI8(i64),
+    #[serde(rename = "vt:int")]
+    VtInt(i32),
+    #[serde(rename = "vt:ui1")]
+    VtUi1(u8),
+    #[serde(rename = "vt:ui2")]
+    VtUi2(u16),
+    #[serde(rename = "vt:ui4")]
+    VtUi4(u32),
+    #[serde(rename = "vt:ui8")]
+    VtUi8(u64),
+    #[serde(rename = "vt:uint")]
+    VtUint(u32),
+    #[serde(rename = "vt:r4")]
+    VtR4(f32),
+    #[serde(rename = "vt:r8")]
+    VtR8(f64),
+    /// A decimal kept as written, see [`Variant::as_f64`].
+    #[serde(rename = "vt:decimal")]
+    VtDecimal(String),
     #[serde(rename = "vt:lpstr")]
     VtLpstr(String),
     #[serde(rename = "vt:lpwstr")]
     VtLpwstr(String),
+    #[serde(rename = "vt:bstr")]
+    VtBstr(String),
+    #[serde(rename = "vt:date", deserialize_with = "deserialize_date_time")]
+    VtDate(DateTime),
+    #[serde(rename = "vt:filetime", deserialize_with = "deserialize_date_time")]
+    VtFiletime(DateTime),
+    #[serde(rename = "vt:bool")]
+    VtBool(bool),
+    /// A currency amount with up to four decimal places, kept as written.
+    #[serde(rename = "vt:cy")]
+    VtCy(String),
+    /// An `HRESULT` error code, written as `0x` followed by hex digits.
+    #[serde(rename = "vt:error", deserialize_with = "deserialize_error")]
+    VtError(u32),
+    /// A class id, e.g. `{00020820-0000-0000-C000-000000000046}`.
+    #[serde(rename = "vt:clsid")]
+    VtClsid(String),
 }
 
 impl Default for Variant {
@@ -38,3 +105,368 @@ impl Default for Variant {
         Variant::VtNull
     }
 }
+
+impl Variant {
+    /// The value of a `vt:variant` wrapper, or the value itself.
+    pub fn inner(&self) -> &Variant {
+        match self {
+            Variant::VtVariant { value } => value.inner(),
+            value => value,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self.inner(), Variant::VtNull | Variant::VtEmpty)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.inner() {
+            Variant::VtBool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of any integer type that fits in an `i64`.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self.inner() {
+            Variant::VtI1(value) => Some(value.into()),
+            Variant::VtI2(value) => Some(value.into()),
+            Variant::VtI4(value) | Variant::VtInt(value) => Some(value.into()),
+            Variant::VtI8(value) => Some(value),
+            Variant::VtUi1(value) => Some(value.into()),
+            Variant::VtUi2(value) => Some(value.into()),
+            Variant::VtUi4(value) | Variant::VtUint(value) => Some(value.into()),
+            Variant::VtUi8(value) => std::convert::TryFrom::try_from(value).ok(),
+            _ => None,
+        }
+    }
+
+    /// The value of any numeric type, including decimals and currency amounts.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self.inner() {
+            Variant::VtR4(value) => Some((*value).into()),
+            Variant::VtR8(value) => Some(*value),
+            Variant::VtDecimal(value) | Variant::VtCy(value) => value.trim().parse().ok(),
+            Variant::VtUi8(value) => Some(*value as f64),
+            value => value.as_i64().map(|value| value as f64),
+        }
+    }
+
+    /// The value of any string type, including class ids.
+    pub fn as_str(&self) -> Option<&str> {
+        match self.inner() {
+            Variant::VtLpstr(value)
+            | Variant::VtLpwstr(value)
+            | Variant::VtBstr(value)
+            | Variant::VtClsid(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_date_time(&self) -> Option<DateTime> {
+        match self.inner() {
+            Variant::VtDate(value) | Variant::VtFiletime(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The decoded data of a binary type.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self.inner() {
+            Variant::VtBlob(value)
+            | Variant::VtOblob(value)
+            | Variant::VtStream(value)
+            | Variant::VtOstream(value)
+            | Variant::VtStorage(value)
+            | Variant::VtOstorage(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The elements of a vector or an array.
+    pub fn as_slice(&self) -> Option<&[Variant]> {
+        match self.inner() {
+            Variant::VtVector { variants, .. } | Variant::VtArray { variants, .. } => {
+                Some(variants)
+            }
+            _ => None,
+        }
+    }
+
+    /// The element name of the value, e.g. `vt:i4`.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            Variant::VtVector { .. } => "vt:vector",
+            Variant::VtArray { .. } => "vt:array",
+            Variant::VtVariant { .. } => "vt:variant",
+            Variant::VtBlob(_) => "vt:blob",
+            Variant::VtOblob(_) => "vt:oblob",
+            Variant::VtStream(_) => "vt:stream",
+            Variant::VtOstream(_) => "vt:ostream",
+            Variant::VtStorage(_) => "vt:storage",
+            Variant::VtOstorage(_) => "vt:ostorage",
+            Variant::VtEmpty => "vt:empty",
+            Variant::VtNull => "vt:null",
+            Variant::VtI1(_) => "vt:i1",
+            Variant::VtI2(_) => "vt:i2",
+            Variant::VtI4(_) => "vt:i4",
+            Variant::VtI8(_) => "vt:i8",
+            Variant::VtInt(_) => "vt:int",
+            Variant::VtUi1(_) => "vt:ui1",
+            Variant::VtUi2(_) => "vt:ui2",
+            Variant::VtUi4(_) => "vt:ui4",
+            Variant::VtUi8(_) => "vt:ui8",
+            Variant::VtUint(_) => "vt:uint",
+            Variant::VtR4(_) => "vt:r4",
+            Variant::VtR8(_) => "vt:r8",
+            Variant::VtDecimal(_) => "vt:decimal",
+            Variant::VtLpstr(_) => "vt:lpstr",
+            Variant::VtLpwstr(_) => "vt:lpwstr",
+            Variant::VtBstr(_) => "vt:bstr",
+            Variant::VtDate(_) => "vt:date",
+            Variant::VtFiletime(_) => "vt:filetime",
+            Variant::VtBool(_) => "vt:bool",
+            Variant::VtCy(_) => "vt:cy",
+            Variant::VtError(_) => "vt:error",
+            Variant::VtClsid(_) => "vt:clsid",
+        }
+    }
+
+    /// Write the value as a `vt` element.
+    pub fn write<W: std::io::Write>(
+        &self,
+        xml: &mut quick_xml::Writer<W>,
+    ) -> Result<(), OoxmlError> {
+        use quick_xml::events::*;
+
+        let tag = self.tag();
+        let mut elem = BytesStart::borrowed_name(tag.as_bytes());
+        let text = match self {
+            Variant::VtVector {
+                size,
+                base_type,
+                variants,
+            } => {
+                elem.push_attribute(("size", size.to_string().as_str()));
+                elem.push_attribute(("baseType", base_type.as_str()));
+                return Self::write_children(xml, elem, variants);
+            }
+            Variant::VtArray {
+                lower_bounds,
+                upper_bounds,
+                base_type,
+                variants,
+            } => {
+                elem.push_attribute(("lBounds", lower_bounds.to_string().as_str()));
+                elem.push_attribute(("uBounds", upper_bounds.to_string().as_str()));
+                elem.push_attribute(("baseType", base_type.as_str()));
+                return Self::write_children(xml, elem, variants);
+            }
+            Variant::VtVariant { value } => {
+                return Self::write_children(xml, elem, std::slice::from_ref(value));
+            }
+            Variant::VtEmpty | Variant::VtNull => {
+                xml.write_event(Event::Empty(elem))?;
+                return Ok(());
+            }
+            Variant::VtBlob(value)
+            | Variant::VtOblob(value)
+            | Variant::VtStream(value)
+            | Variant::VtOstream(value)
+            | Variant::VtStorage(value)
+            | Variant::VtOstorage(value) => BASE64.encode(value),
+            Variant::VtI1(value) => value.to_string(),
+            Variant::VtI2(value) => value.to_string(),
+            Variant::VtI4(value) | Variant::VtInt(value) => value.to_string(),
+            Variant::VtI8(value) => value.to_string(),
+            Variant::VtUi1(value) => value.to_string(),
+            Variant::VtUi2(value) => value.to_string(),
+            Variant::VtUi4(value) | Variant::VtUint(value) => value.to_string(),
+            Variant::VtUi8(value) => value.to_string(),
+            Variant::VtR4(value) => float_text(*value),
+            Variant::VtR8(value) => float_text(*value),
+            Variant::VtDate(value) | Variant::VtFiletime(value) => {
+                value.to_rfc3339_opts(SecondsFormat::Secs, true)
+            }
+            Variant::VtBool(value) => value.to_string(),
+            Variant::VtError(value) => format!("0x{:08X}", value),
+            Variant::VtDecimal(value)
+            | Variant::VtLpstr(value)
+            | Variant::VtLpwstr(value)
+            | Variant::VtBstr(value)
+            | Variant::VtCy(value)
+            | Variant::VtClsid(value) => value.clone(),
+        };
+        xml.write_event(Event::Start(elem))?;
+        xml.write_event(Event::Text(BytesText::from_plain_str(&text)))?;
+        xml.write_event(Event::End(BytesEnd::borrowed(tag.as_bytes())))?;
+        Ok(())
+    }
+
+    fn write_children<W: std::io::Write>(
+        xml: &mut quick_xml::Writer<W>,
+        elem: quick_xml::events::BytesStart,
+        variants: &[Variant],
+    ) -> Result<(), OoxmlError> {
+        use quick_xml::events::*;
+
+        let end = BytesEnd::owned(elem.name().to_vec());
+        xml.write_event(Event::Start(elem))?;
+        for variant in variants {
+            variant.write(xml)?;
+        }
+        xml.write_event(Event::End(end))?;
+        Ok(())
+    }
+}
+
+impl From<bool> for Variant {
+    fn from(value: bool) -> Self {
+        Variant::VtBool(value)
+    }
+}
+
+impl From<i32> for Variant {
+    fn from(value: i32) -> Self {
+        Variant::VtI4(value)
+    }
+}
+
+impl From<i64> for Variant {
+    fn from(value: i64) -> Self {
+        Variant::VtI8(value)
+    }
+}
+
+impl From<f64> for Variant {
+    fn from(value: f64) -> Self {
+        Variant::VtR8(value)
+    }
+}
+
+impl From<&str> for Variant {
+    fn from(value: &str) -> Self {
+        Variant::VtLpwstr(value.to_string())
+    }
+}
+
+impl From<String> for Variant {
+    fn from(value: String) -> Self {
+        Variant::VtLpwstr(value)
+    }
+}
+
+impl From<DateTime> for Variant {
+    fn from(value: DateTime) -> Self {
+        Variant::VtFiletime(value)
+    }
+}
+
+fn deserialize_binary<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    let value: String = value.split_whitespace().collect();
+    BASE64.decode(value).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_date_time<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_w3cdtf(&value)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid date {:?}", value)))
+}
+
+fn deserialize_error<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    let hex = value.trim();
+    let hex = hex
+        .strip_prefix("0x")
+        .or_else(|| hex.strip_prefix("0X"))
+        .unwrap_or(hex);
+    u32::from_str_radix(hex, 16)
+        .map_err(|_| serde::de::Error::custom(format!("invalid error code {:?}", value)))
+}
+
+/// Text of an `xsd:float` or `xsd:double`, whose special values are `INF`, `-INF` and
+/// `NaN`.
+fn float_text<F: Into<f64> + ToString + Copy>(value: F) -> String {
+    let double: f64 = value.into();
+    if double.is_nan() {
+        "NaN".to_string()
+    } else if double == f64::INFINITY {
+        "INF".to_string()
+    } else if double == f64::NEG_INFINITY {
+        "-INF".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[test]
+fn variant_values() {
+    use chrono::{TimeZone, Utc};
+
+    let parse = |xml: &str| -> Variant { quick_xml::de::from_str(xml).unwrap() };
+    assert_eq!(
+        parse("<vt:i4>2147483647</vt:i4>").as_i64(),
+        Some(2147483647)
+    );
+    assert_eq!(
+        parse("<vt:i8>-9000000000</vt:i8>").as_i64(),
+        Some(-9000000000)
+    );
+    assert_eq!(parse("<vt:ui2>65535</vt:ui2>").as_i64(), Some(65535));
+    assert_eq!(parse("<vt:r8>1.5</vt:r8>").as_f64(), Some(1.5));
+    assert_eq!(
+        parse("<vt:decimal>12.25</vt:decimal>").as_f64(),
+        Some(12.25)
+    );
+    assert_eq!(parse("<vt:cy>-3.5</vt:cy>"), Variant::VtCy("-3.5".into()));
+    assert_eq!(parse("<vt:bool>true</vt:bool>").as_bool(), Some(true));
+    assert_eq!(parse("<vt:bstr>text</vt:bstr>").as_str(), Some("text"));
+    assert_eq!(
+        parse("<vt:filetime>2021-07-07T02:32:26Z</vt:filetime>").as_date_time(),
+        Some(Utc.with_ymd_and_hms(2021, 7, 7, 2, 32, 26).unwrap())
+    );
+    assert_eq!(
+        parse("<vt:error>0x80004005</vt:error>"),
+        Variant::VtError(0x8000_4005)
+    );
+    assert_eq!(
+        parse("<vt:blob>AQID</vt:blob>").as_bytes(),
+        Some(&[1u8, 2, 3][..])
+    );
+    assert!(parse("<vt:empty/>").is_null());
+
+    let vector = parse(
+        r#"<vt:vector size="2" baseType="variant"><vt:variant><vt:lpstr>Worksheets</vt:lpstr></vt:variant><vt:variant><vt:i4>2</vt:i4></vt:variant></vt:vector>"#,
+    );
+    let values = vector.as_slice().unwrap();
+    assert_eq!(values[0].as_str(), Some("Worksheets"));
+    assert_eq!(values[1].as_i64(), Some(2));
+
+    let array = parse(
+        r#"<vt:array lBounds="0" uBounds="1" baseType="i4"><vt:i4>1</vt:i4><vt:i4>2</vt:i4></vt:array>"#,
+    );
+    assert_eq!(array.as_slice().map(<[Variant]>::len), Some(2));
+
+    let write = |variant: &Variant| {
+        let mut xml = quick_xml::Writer::new(Vec::new());
+        variant.write(&mut xml).unwrap();
+        String::from_utf8(xml.into_inner()).unwrap()
+    };
+    for (variant, text) in [
+        (Variant::VtR8(f64::INFINITY), "<vt:r8>INF</vt:r8>"),
+        (Variant::VtR8(f64::NEG_INFINITY), "<vt:r8>-INF</vt:r8>"),
+        (Variant::VtR4(f32::INFINITY), "<vt:r4>INF</vt:r4>"),
+        (Variant::VtR4(f32::NEG_INFINITY), "<vt:r4>-INF</vt:r4>"),
+        (Variant::VtR8(1.5), "<vt:r8>1.5</vt:r8>"),
+        (Variant::VtR4(0.1), "<vt:r4>0.1</vt:r4>"),
+    ] {
+        assert_eq!(write(&variant), text);
+        assert_eq!(parse(text), variant);
+    }
+    for variant in [Variant::VtR8(f64::NAN), Variant::VtR4(f32::NAN)] {
+        let text = write(&variant);
+        assert!(text.contains(">NaN<"), "{}", text);
+        assert!(parse(&text).as_f64().unwrap().is_nan());
+    }
+}
@@ -0,0 +1,639 @@
+//! A minimal XML tree, used where parts have to be processed as generic XML rather than
+//! deserialized into typed models, e.g. to canonicalize signed content.
+
+use std::collections::BTreeMap;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use crate::error::OoxmlError;
+
+pub(crate) const XML_NAMESPACE_URI: &str = "http://www.w3.org/XML/1998/namespace";
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum XmlNode {
+    Element(XmlElement),
+    Text(String),
+    Comment(String),
+    ProcessingInstruction(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub(crate) struct XmlElement {
+    /// Qualified name, e.g. `mdssi:SignatureTime`.
+    pub name: String,
+    /// Attributes with qualified names and unescaped values, namespace declarations
+    /// included.
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlNode>,
+}
+
+/// Whether canonicalization renders every in-scope namespace or only the used ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum C14nMode {
+    /// Canonical XML 1.0 (`http://www.w3.org/TR/2001/REC-xml-c14n-20010315`).
+    Inclusive,
+    /// Exclusive XML Canonicalization 1.0 (`http://www.w3.org/2001/10/xml-exc-c14n#`).
+    Exclusive,
+}
+
+/// Namespace declarations in scope, prefix (empty for the default namespace) → URI.
+pub(crate) type NamespaceScope = BTreeMap<String, String>;
+
+impl XmlElement {
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Parse the document element of `xml`.
+    pub fn parse(xml: &[u8]) -> Result<Self, OoxmlError> {
+        let mut reader = Reader::from_reader(xml);
+        reader.trim_text(false);
+        let mut buf = Vec::new();
+        let mut stack: Vec<XmlElement> = Vec::new();
+        loop {
+            match reader.read_event(&mut buf)? {
+                Event::Start(e) => stack.push(Self::from_start(&e)?),
+                Event::Empty(e) => {
+                    let element = Self::from_start(&e)?;
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(XmlNode::Element(element)),
+                        None => return Ok(element),
+                    }
+                }
+                Event::End(_) => {
+                    let element = stack.pop().expect("end event of an open element");
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(XmlNode::Element(element)),
+                        None => return Ok(element),
+                    }
+                }
+                Event::Text(e) => {
+                    if let Some(parent) = stack.last_mut() {
+                        let text = unescape(&normalize_line_endings(&e))?;
+                        parent.children.push(XmlNode::Text(text));
+                    }
+                }
+                Event::CData(e) => {
+                    if let Some(parent) = stack.last_mut() {
+                        let text = normalize_line_endings(&e);
+                        let text = String::from_utf8_lossy(&text).into_owned();
+                        parent.children.push(XmlNode::Text(text));
+                    }
+                }
+                Event::Comment(e) => {
+                    if let Some(parent) = stack.last_mut() {
+                        let text = String::from_utf8_lossy(&e).into_owned();
+                        parent.children.push(XmlNode::Comment(text));
+                    }
+                }
+                Event::PI(e) => {
+                    if let Some(parent) = stack.last_mut() {
+                        let text = String::from_utf8_lossy(&e).into_owned();
+                        parent.children.push(XmlNode::ProcessingInstruction(text));
+                    }
+                }
+                Event::Decl(_) | Event::DocType(_) => {}
+                Event::Eof => {
+                    return Err(OoxmlError::XmlError(quick_xml::Error::UnexpectedEof(
+                        "document element".into(),
+                    )))
+                }
+            }
+            buf.clear();
+        }
+    }
+
+    fn from_start(e: &quick_xml::events::BytesStart) -> Result<Self, OoxmlError> {
+        let mut element = Self::new(String::from_utf8_lossy(e.name()));
+        for attribute in e.attributes() {
+            let attribute = attribute?;
+            let value = attribute_value_normalize(&attribute.value);
+            element.attributes.push((
+                String::from_utf8_lossy(attribute.key).into_owned(),
+                unescape(&value)?,
+            ));
+        }
+        Ok(element)
+    }
+
+    pub fn prefix(&self) -> &str {
+        prefix(&self.name)
+    }
+
+    pub fn local_name(&self) -> &str {
+        local_name(&self.name)
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn set_attribute<K: Into<String>, V: Into<String>>(&mut self, name: K, value: V) {
+        let name = name.into();
+        let value = value.into();
+        match self.attributes.iter_mut().find(|(key, _)| *key == name) {
+            Some(attribute) => attribute.1 = value,
+            None => self.attributes.push((name, value)),
+        }
+    }
+
+    pub fn push_child(&mut self, child: XmlElement) -> &mut XmlElement {
+        self.children.push(XmlNode::Element(child));
+        match self.children.last_mut() {
+            Some(XmlNode::Element(child)) => child,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn push_text<S: Into<String>>(&mut self, text: S) {
+        self.children.push(XmlNode::Text(text.into()));
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &XmlElement> {
+        self.children.iter().filter_map(|child| match child {
+            XmlNode::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// Get the first child element with local name `local_name`.
+    pub fn child(&self, local_name: &str) -> Option<&XmlElement> {
+        self.elements().find(|e| e.local_name() == local_name)
+    }
+
+    /// Get the child elements with local name `local_name`.
+    pub fn children_named<'a>(
+        &'a self,
+        local_name: &'a str,
+    ) -> impl Iterator<Item = &'a XmlElement> {
+        self.elements()
+            .filter(move |e| e.local_name() == local_name)
+    }
+
+    /// Find the first descendant element in document order with local name `local_name`.
+    pub fn find(&self, local_name: &str) -> Option<&XmlElement> {
+        self.elements().find_map(|child| {
+            if child.local_name() == local_name {
+                Some(child)
+            } else {
+                child.find(local_name)
+            }
+        })
+    }
+
+    /// The concatenated text content of this element and its descendants.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        self.collect_text(&mut text);
+        text
+    }
+
+    fn collect_text(&self, text: &mut String) {
+        for child in &self.children {
+            match child {
+                XmlNode::Text(t) => text.push_str(t),
+                XmlNode::Element(e) => e.collect_text(text),
+                _ => {}
+            }
+        }
+    }
+
+    /// Find the first element in document order with attribute `Id` equal to `id`, together
+    /// with the namespaces and `xml:*` attributes in scope of its parent.
+    pub fn find_by_id(&self, id: &str) -> Option<(&XmlElement, NamespaceScope, XmlAttributes)> {
+        self.find_by_id_in(id, &NamespaceScope::new(), &XmlAttributes::new())
+    }
+
+    fn find_by_id_in(
+        &self,
+        id: &str,
+        scope: &NamespaceScope,
+        xml_attributes: &XmlAttributes,
+    ) -> Option<(&XmlElement, NamespaceScope, XmlAttributes)> {
+        if self.attribute("Id") == Some(id) {
+            return Some((self, scope.clone(), xml_attributes.clone()));
+        }
+        let scope = self.scope(scope);
+        let xml_attributes = self.xml_attributes(xml_attributes);
+        self.elements()
+            .find_map(|child| child.find_by_id_in(id, &scope, &xml_attributes))
+    }
+
+    /// The namespace scope of this element's content, given the scope of its parent.
+    pub fn scope(&self, parent: &NamespaceScope) -> NamespaceScope {
+        let mut scope = parent.clone();
+        for (key, value) in &self.attributes {
+            if let Some(prefix) = namespace_declaration(key) {
+                scope.insert(prefix.to_string(), value.clone());
+            }
+        }
+        scope
+    }
+
+    /// The `xml:*` attributes in scope of this element's content.
+    pub fn xml_attributes(&self, parent: &XmlAttributes) -> XmlAttributes {
+        let mut attributes = parent.clone();
+        for (key, value) in &self.attributes {
+            if key.starts_with("xml:") {
+                attributes.insert(key.clone(), value.clone());
+            }
+        }
+        attributes
+    }
+
+    /// Serialize the element as an XML document.
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#);
+        xml.push_str("\r\n");
+        self.write_to(&mut xml);
+        xml
+    }
+
+    fn write_to(&self, xml: &mut String) {
+        xml.push('<');
+        xml.push_str(&self.name);
+        for (key, value) in &self.attributes {
+            xml.push(' ');
+            xml.push_str(key);
+            xml.push_str("=\"");
+            escape_attribute(value, xml);
+            xml.push('"');
+        }
+        if self.children.is_empty() {
+            xml.push_str("/>");
+            return;
+        }
+        xml.push('>');
+        for child in &self.children {
+            match child {
+                XmlNode::Element(e) => e.write_to(xml),
+                XmlNode::Text(t) => escape_text(t, xml),
+                XmlNode::Comment(c) => {
+                    xml.push_str("<!--");
+                    xml.push_str(c);
+                    xml.push_str("-->");
+                }
+                XmlNode::ProcessingInstruction(pi) => {
+                    xml.push_str("<?");
+                    xml.push_str(pi);
+                    xml.push_str("?>");
+                }
+            }
+        }
+        xml.push_str("</");
+        xml.push_str(&self.name);
+        xml.push('>');
+    }
+
+    /// Canonicalize this element as a document subset without comments.
+    ///
+    /// `scope` and `xml_attributes` are the namespaces and `xml:*` attributes in scope of the
+    /// element's parent, which inclusive canonicalization renders on the element itself.
+    pub fn canonicalize(
+        &self,
+        mode: C14nMode,
+        scope: &NamespaceScope,
+        xml_attributes: &XmlAttributes,
+    ) -> Vec<u8> {
+        let mut output = String::new();
+        let inherited = match mode {
+            C14nMode::Inclusive => xml_attributes.clone(),
+            C14nMode::Exclusive => XmlAttributes::new(),
+        };
+        self.canonicalize_into(mode, scope, &NamespaceScope::new(), &inherited, &mut output);
+        output.into_bytes()
+    }
+
+    fn canonicalize_into(
+        &self,
+        mode: C14nMode,
+        parent_scope: &NamespaceScope,
+        rendered: &NamespaceScope,
+        inherited_xml_attributes: &XmlAttributes,
+        output: &mut String,
+    ) {
+        let scope = self.scope(parent_scope);
+
+        let candidates: Vec<&str> = match mode {
+            C14nMode::Inclusive => scope.keys().map(String::as_str).collect(),
+            C14nMode::Exclusive => {
+                let mut used = vec![self.prefix()];
+                used.extend(
+                    self.attributes
+                        .iter()
+                        .filter(|(key, _)| namespace_declaration(key).is_none())
+                        .map(|(key, _)| prefix(key))
+                        .filter(|prefix| !prefix.is_empty()),
+                );
+                used
+            }
+        };
+        let mut namespaces = BTreeMap::new();
+        for prefix in candidates {
+            if prefix == "xml" {
+                continue;
+            }
+            let uri = scope.get(prefix).map(String::as_str).unwrap_or_default();
+            let rendered_uri = rendered.get(prefix).map(String::as_str).unwrap_or_default();
+            if uri != rendered_uri {
+                namespaces.insert(prefix, uri);
+            }
+        }
+        let mut rendered = rendered.clone();
+        for (prefix, uri) in &namespaces {
+            rendered.insert(prefix.to_string(), uri.to_string());
+        }
+
+        let mut attributes: Vec<(&str, &str, &str, &str)> = Vec::new();
+        for (key, value) in &self.attributes {
+            if namespace_declaration(key).is_some() {
+                continue;
+            }
+            let prefix = prefix(key);
+            let uri = match prefix {
+                "" => "",
+                "xml" => XML_NAMESPACE_URI,
+                prefix => scope.get(prefix).map(String::as_str).unwrap_or_default(),
+            };
+            attributes.push((uri, local_name(key), key, value));
+        }
+        for (key, value) in inherited_xml_attributes {
+            if self.attribute(key).is_none() {
+                attributes.push((XML_NAMESPACE_URI, local_name(key), key, value));
+            }
+        }
+        attributes.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+        output.push('<');
+        output.push_str(&self.name);
+        for (prefix, uri) in &namespaces {
+            output.push_str(" xmlns");
+            if !prefix.is_empty() {
+                output.push(':');
+                output.push_str(prefix);
+            }
+            output.push_str("=\"");
+            escape_attribute(uri, output);
+            output.push('"');
+        }
+        for (_, _, key, value) in attributes {
+            output.push(' ');
+            output.push_str(key);
+            output.push_str("=\"");
+            escape_attribute(value, output);
+            output.push('"');
+        }
+        output.push('>');
+
+        for child in &self.children {
+            match child {
+                XmlNode::Element(e) => {
+                    e.canonicalize_into(mode, &scope, &rendered, &XmlAttributes::new(), output)
+                }
+                XmlNode::Text(t) => escape_text(t, output),
+                XmlNode::ProcessingInstruction(pi) => {
+                    output.push_str("<?");
+                    output.push_str(pi);
+                    output.push_str("?>");
+                }
+                XmlNode::Comment(_) => {}
+            }
+        }
+
+        output.push_str("</");
+        output.push_str(&self.name);
+        output.push('>');
+    }
+}
+
+/// `xml:*` attributes in scope, qualified name → value.
+pub(crate) type XmlAttributes = BTreeMap<String, String>;
+
+/// Whether `data` looks like XML and nests elements deeper than `limit`.
+///
+/// Malformed XML is not reported here, it fails when the part is parsed.
+pub(crate) fn exceeds_depth(data: &[u8], limit: usize) -> bool {
+    let text = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    if text.iter().find(|b| !b.is_ascii_whitespace()) != Some(&b'<') {
+        return false;
+    }
+    let mut reader = Reader::from_reader(text);
+    let mut buf = Vec::new();
+    let mut depth = 0usize;
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(_)) => {
+                depth += 1;
+                if depth > limit {
+                    return true;
+                }
+            }
+            Ok(Event::Empty(_)) if depth + 1 > limit => return true,
+            Ok(Event::End(_)) => depth = depth.saturating_sub(1),
+            Ok(Event::Eof) | Err(_) => return false,
+            Ok(_) => {}
+        }
+        buf.clear();
+    }
+}
+
+/// Carry the content of `original` that a model does not know over to `generated`, the
+/// model's serialization of the same part.
+///
+/// Attributes of the document element missing from `generated` are kept, and child
+/// elements whose local name is not one of `known` are inserted after the known sibling
+/// they followed in `original`. `generated` is returned as is when there is nothing to
+/// keep or `original` is not well-formed.
+pub(crate) fn preserve_unknown(
+    original: &[u8],
+    generated: Vec<u8>,
+    known: &[&str],
+) -> Result<Vec<u8>, OoxmlError> {
+    let original = match XmlElement::parse(original) {
+        Ok(original) => original,
+        Err(_) => return Ok(generated),
+    };
+    let mut element = XmlElement::parse(&generated)?;
+    if original.local_name() != element.local_name() {
+        return Ok(generated);
+    }
+
+    let mut changed = false;
+    for (name, value) in &original.attributes {
+        if element.attribute(name).is_none() {
+            element.attributes.push((name.clone(), value.clone()));
+            changed = true;
+        }
+    }
+
+    // The n-th known child of a name in `original` is the n-th of that name in `generated`.
+    let mut occurrences: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut cursor = 0;
+    for child in original.elements() {
+        let local_name = child.local_name();
+        if known
+            .iter()
+            .any(|known| self::local_name(known) == local_name)
+        {
+            let occurrence = occurrences.entry(local_name).or_default();
+            let position = element
+                .children
+                .iter()
+                .enumerate()
+                .filter(
+                    |(_, node)| matches!(node, XmlNode::Element(e) if e.local_name() == local_name),
+                )
+                .nth(*occurrence)
+                .map(|(position, _)| position);
+            *occurrence += 1;
+            if let Some(position) = position {
+                cursor = position + 1;
+            }
+        } else {
+            element
+                .children
+                .insert(cursor, XmlNode::Element(child.clone()));
+            cursor += 1;
+            changed = true;
+        }
+    }
+
+    if !changed {
+        return Ok(generated);
+    }
+    Ok(element.to_xml().into_bytes())
+}
+
+pub(crate) fn prefix(name: &str) -> &str {
+    name.split_once(':').map(|(prefix, _)| prefix).unwrap_or("")
+}
+
+pub(crate) fn local_name(name: &str) -> &str {
+    name.split_once(':').map(|(_, local)| local).unwrap_or(name)
+}
+
+/// The declared prefix if `key` is a namespace declaration attribute, `""` for `xmlns`.
+pub(crate) fn namespace_declaration(key: &str) -> Option<&str> {
+    if key == "xmlns" {
+        Some("")
+    } else {
+        key.strip_prefix("xmlns:")
+    }
+}
+
+fn normalize_line_endings(raw: &[u8]) -> Vec<u8> {
+    let mut normalized = Vec::with_capacity(raw.len());
+    let mut iter = raw.iter().peekable();
+    while let Some(&byte) = iter.next() {
+        if byte == b'\r' {
+            if iter.peek() == Some(&&b'\n') {
+                iter.next();
+            }
+            normalized.push(b'\n');
+        } else {
+            normalized.push(byte);
+        }
+    }
+    normalized
+}
+
+/// Replace literal whitespace in an attribute value by spaces (XML 1.0, 3.3.3).
+fn attribute_value_normalize(raw: &[u8]) -> Vec<u8> {
+    normalize_line_endings(raw)
+        .into_iter()
+        .map(|byte| match byte {
+            b'\t' | b'\n' => b' ',
+            byte => byte,
+        })
+        .collect()
+}
+
+fn unescape(raw: &[u8]) -> Result<String, OoxmlError> {
+    let unescaped = quick_xml::escape::unescape(raw).map_err(quick_xml::Error::EscapeError)?;
+    Ok(String::from_utf8_lossy(&unescaped).into_owned())
+}
+
+fn escape_attribute(value: &str, output: &mut String) {
+    for ch in value.chars() {
+        match ch {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '"' => output.push_str("&quot;"),
+            '\t' => output.push_str("&#x9;"),
+            '\n' => output.push_str("&#xA;"),
+            '\r' => output.push_str("&#xD;"),
+            ch => output.push(ch),
+        }
+    }
+}
+
+fn escape_text(text: &str, output: &mut String) {
+    for ch in text.chars() {
+        match ch {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '\r' => output.push_str("&#xD;"),
+            ch => output.push(ch),
+        }
+    }
+}
+
+#[test]
+fn canonicalize_subtree() {
+    let xml = br#"<?xml version="1.0"?>
+<a:Root xmlns:a="urn:a" xmlns="urn:d" xml:lang="en"><Child b="2" a="1" xmlns:a="urn:a"><!-- c --><x:Empty xmlns:x="urn:x" x:y="&#xD;"/>a &amp; b
+</Child></a:Root>"#;
+    let root = XmlElement::parse(xml).unwrap();
+    let child = root.child("Child").unwrap();
+    let scope = root.scope(&NamespaceScope::new());
+    let xml_attributes = root.xml_attributes(&XmlAttributes::new());
+
+    let inclusive = child.canonicalize(C14nMode::Inclusive, &scope, &xml_attributes);
+    assert_eq!(
+        String::from_utf8(inclusive).unwrap(),
+        "<Child xmlns=\"urn:d\" xmlns:a=\"urn:a\" a=\"1\" b=\"2\" xml:lang=\"en\">\
+         <x:Empty xmlns:x=\"urn:x\" x:y=\"&#xD;\"></x:Empty>a &amp; b\n</Child>"
+    );
+
+    let exclusive = child.canonicalize(C14nMode::Exclusive, &scope, &xml_attributes);
+    assert_eq!(
+        String::from_utf8(exclusive).unwrap(),
+        "<Child xmlns=\"urn:d\" a=\"1\" b=\"2\">\
+         <x:Empty xmlns:x=\"urn:x\" x:y=\"&#xD;\"></x:Empty>a &amp; b\n</Child>"
+    );
+}
+
+#[test]
+fn xml_depth() {
+    let nested = format!("{}{}", "<a>".repeat(10), "</a>".repeat(10));
+    assert!(!exceeds_depth(nested.as_bytes(), 10));
+    assert!(exceeds_depth(nested.as_bytes(), 9));
+    assert!(exceeds_depth(b"<a><b/></a>", 1));
+    assert!(!exceeds_depth(b"\x89PNG<a><b/></a>", 1));
+}
+
+#[test]
+fn preserve_unknown_content() {
+    let original = br#"<Types xmlns="urn:t" xmlns:x="urn:x" x:flag="1"><Default Extension="xml"/><x:ext/><Override PartName="/a"/><Override PartName="/b"/><x:last/></Types>"#;
+    let generated = br#"<Types xmlns="urn:t"><Default Extension="xml"/><Override PartName="/a"/><Override PartName="/c"/></Types>"#;
+    let merged = preserve_unknown(original, generated.to_vec(), &["Default", "Override"]).unwrap();
+    let merged = XmlElement::parse(&merged).unwrap();
+    let names: Vec<&str> = merged.elements().map(|e| e.name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["Default", "x:ext", "Override", "Override", "x:last"]
+    );
+    assert_eq!(merged.attribute("x:flag"), Some("1"));
+    assert_eq!(merged.attribute("xmlns:x"), Some("urn:x"));
+
+    let unchanged = preserve_unknown(generated, generated.to_vec(), &["Default", "Override"]);
+    assert_eq!(unchanged.unwrap(), generated.to_vec());
+}
@@ -1,7 +1,15 @@
 #[cfg(test)]
 mod tests {
-    use opc::document::sheet::SpreadsheetDocument;
+    use opc::document::sheet::{SpreadsheetDocument, SpreadsheetDocumentType};
+    use opc::packaging::namespace::Conformance;
+    use opc::packaging::options::OpenOptions;
     use opc::packaging::package::OpenXmlPackage;
+    use opc::packaging::signature::{ReferenceStatus, SignOptions, SigningKey};
+    use opc::packaging::validation::DiagnosticKind;
+    use opc::packaging::variant::Variant;
+    use opc::OoxmlError;
+
+    use chrono::{TimeZone, Utc};
 
     #[test]
     #[ignore = "passed"]
@@ -11,6 +19,1181 @@ mod tests {
         println!("{:?}", package);
     }
 
+    #[test]
+    fn test_package_save() {
+        let path = "resources/files/excel-demo/demo.xlsx";
+        let package = OpenXmlPackage::open(path).unwrap();
+        let output = std::env::temp_dir().join("opc-test-package-save.xlsx");
+        package.save(&output).unwrap();
+
+        let saved = OpenXmlPackage::open(&output).unwrap();
+        let part = saved.get_part("xl/workbook.xml").unwrap();
+        assert_eq!(
+            part.data().unwrap(),
+            package.get_part("xl/workbook.xml").unwrap().data().unwrap()
+        );
+        assert!(saved.has_content_types());
+        assert!(saved.has_relationships());
+
+        let document = SpreadsheetDocument::open(&output).unwrap();
+        assert_eq!(document.get_workbook().worksheet_names().len(), 2);
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn test_open_from_bytes() {
+        let bytes = std::fs::read("resources/files/excel-demo/demo.xlsx").unwrap();
+        let package = OpenXmlPackage::from_bytes(&bytes).unwrap();
+        assert!(package.get_part("xl/workbook.xml").is_some());
+
+        let document = SpreadsheetDocument::from_reader(std::io::Cursor::new(bytes)).unwrap();
+        assert_eq!(document.get_workbook().worksheet_names().len(), 2);
+    }
+
+    #[test]
+    fn test_open_lazy() {
+        let path = "resources/files/excel-demo/demo.xlsx";
+        let package = OpenXmlPackage::open_with_options(path, OpenOptions::lazy()).unwrap();
+        let image = package.get_part("xl/media/image1.png").unwrap();
+        assert!(!image.is_loaded());
+        assert_eq!(&image.data().unwrap()[1..4], b"PNG");
+        assert!(image.is_loaded());
+
+        let document = SpreadsheetDocument::open_with_options(path, OpenOptions::lazy()).unwrap();
+        assert_eq!(document.get_workbook().worksheet_names().len(), 2);
+    }
+
+    #[test]
+    fn test_part_relationships() {
+        const DRAWING: &str =
+            "http://schemas.openxmlformats.org/officeDocument/2006/relationships/drawing";
+        const IMAGE: &str =
+            "http://schemas.openxmlformats.org/officeDocument/2006/relationships/image";
+        let path = "resources/files/excel-demo/demo.xlsx";
+        let package = OpenXmlPackage::open(path).unwrap();
+
+        let relationships = package
+            .relationships_of("/xl/worksheets/sheet1.xml")
+            .unwrap();
+        assert_eq!(relationships.len(), 1);
+
+        let images: Vec<_> = ["xl/worksheets/sheet1.xml", "xl/worksheets/sheet2.xml"]
+            .iter()
+            .flat_map(|sheet| package.related_parts(sheet, DRAWING))
+            .flat_map(|drawing| drawing.related_parts(&package, IMAGE))
+            .collect();
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].uri().to_str(), Some("xl/media/image1.png"));
+    }
+
+    #[test]
+    fn test_part_content_types() {
+        const WORKSHEET: &str =
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml";
+        let path = "resources/files/excel-demo/demo.xlsx";
+        let package = OpenXmlPackage::open(path).unwrap();
+
+        let image = package.get_part("/xl/media/image1.png").unwrap();
+        assert_eq!(
+            image.content_type().map(|ct| ct.as_str()),
+            Some("image/png")
+        );
+        assert_eq!(package.parts_by_content_type(WORKSHEET).count(), 2);
+        let worksheet = WORKSHEET.to_ascii_uppercase();
+        assert_eq!(package.parts_by_content_type(&worksheet).count(), 2);
+
+        let document = SpreadsheetDocument::open(path).unwrap();
+        assert_eq!(document.document_type(), SpreadsheetDocumentType::Workbook);
+    }
+
+    fn zip_package(files: &[(&str, &str)]) -> Vec<u8> {
+        use std::io::Write;
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        for (name, data) in files.iter() {
+            zip.start_file(*name, options).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let bytes = zip_package(&[
+            (
+                "[Content_Types].xml",
+                r#"<Types><Default Extension="xml" ContentType="application/xml"/></Types>"#,
+            ),
+            (
+                "_rels/.rels",
+                r#"<Relationships><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/workbook.xml",
+                r#"<workbook><bookViews><workbookView/></bookViews><workbookPr/><sheets><sheet name="a" sheetId="x" r:id="rId1"/></sheets></workbook>"#,
+            ),
+        ]);
+
+        match SpreadsheetDocument::from_bytes(bytes).unwrap_err() {
+            OoxmlError::PartParseError { part, path, .. } => {
+                assert_eq!(part, "/xl/workbook.xml");
+                assert!(path.starts_with("sheets"), "{}", path);
+            }
+            e => panic!("unexpected error: {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_markup_compatibility() {
+        let bytes = zip_package(&[
+            (
+                "[Content_Types].xml",
+                r#"<Types><Default Extension="xml" ContentType="application/xml"/></Types>"#,
+            ),
+            (
+                "_rels/.rels",
+                r#"<Relationships><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<Relationships><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/workbook.xml",
+                r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:mc="http://schemas.openxmlformats.org/markup-compatibility/2006" xmlns:x15="http://schemas.microsoft.com/office/spreadsheetml/2010/11/main" mc:Ignorable="x15"><workbookPr/><mc:AlternateContent><mc:Choice Requires="x15"><x15ac:absPath xmlns:x15ac="http://schemas.microsoft.com/office/spreadsheetml/2010/11/ac" url="C:\"/></mc:Choice></mc:AlternateContent><bookViews><workbookView/></bookViews><sheets><sheet name="a" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+            ),
+            (
+                "xl/worksheets/sheet1.xml",
+                r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:mc="http://schemas.openxmlformats.org/markup-compatibility/2006" xmlns:x14ac="http://schemas.microsoft.com/office/spreadsheetml/2009/9/ac" xmlns:x14="http://schemas.microsoft.com/office/spreadsheetml/2009/9/main" mc:Ignorable="x14ac"><sheetFormatPr defaultRowHeight="15" x14ac:dyDescent="0.25"/><sheetData><row r="1" x14ac:dyDescent="0.25"><mc:AlternateContent><mc:Choice Requires="x14"><x14:c r="A1"/></mc:Choice><mc:Fallback><c r="A1"><v>42</v></c></mc:Fallback></mc:AlternateContent></row></sheetData></worksheet>"#,
+            ),
+        ]);
+
+        let document = SpreadsheetDocument::from_bytes(bytes).unwrap();
+        let workbook = document.get_workbook();
+        assert_eq!(workbook.worksheet_names(), ["a"]);
+        let rows: Vec<Vec<String>> = workbook.worksheets()[0]
+            .rows()
+            .map(|row| {
+                row.into_iter()
+                    .map(|cell| cell.value().unwrap_or_default().to_string())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(rows, [["42"]]);
+    }
+
+    #[test]
+    fn test_strict_conformance() {
+        let bytes = zip_package(&[
+            (
+                "[Content_Types].xml",
+                r#"<Types><Default Extension="xml" ContentType="application/xml"/></Types>"#,
+            ),
+            (
+                "_rels/.rels",
+                r#"<Relationships><Relationship Id="rId1" Type="http://purl.oclc.org/ooxml/officeDocument/relationships/officeDocument" Target="xl/workbook.xml"/><Relationship Id="rId2" Type="http://purl.oclc.org/ooxml/officeDocument/relationships/extendedProperties" Target="docProps/app.xml"/></Relationships>"#,
+            ),
+            (
+                "docProps/app.xml",
+                r#"<Properties xmlns="http://purl.oclc.org/ooxml/officeDocument/extendedProperties"><Application>Microsoft Excel</Application></Properties>"#,
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<Relationships><Relationship Id="rId1" Type="http://purl.oclc.org/ooxml/officeDocument/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/workbook.xml",
+                r#"<workbook xmlns="http://purl.oclc.org/ooxml/spreadsheetml/main" xmlns:r="http://purl.oclc.org/ooxml/officeDocument/relationships" conformance="strict"><workbookPr/><bookViews><workbookView/></bookViews><sheets><sheet name="strict" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+            ),
+            (
+                "xl/worksheets/sheet1.xml",
+                r#"<worksheet xmlns="http://purl.oclc.org/ooxml/spreadsheetml/main"><sheetData/></worksheet>"#,
+            ),
+        ]);
+
+        let document = SpreadsheetDocument::from_bytes(bytes).unwrap();
+        assert_eq!(document.conformance(), Conformance::Strict);
+        assert_eq!(document.package().conformance(), Conformance::Strict);
+        assert_eq!(document.get_workbook().worksheet_names(), ["strict"]);
+        assert_eq!(
+            document.package().app_properties().application.as_deref(),
+            Some("Microsoft Excel")
+        );
+
+        let document = SpreadsheetDocument::open("resources/files/excel-demo/demo.xlsx").unwrap();
+        assert_eq!(document.conformance(), Conformance::Transitional);
+        assert_eq!(document.package().conformance(), Conformance::Transitional);
+    }
+
+    #[test]
+    fn test_flat_opc() {
+        let mut package = OpenXmlPackage::open("resources/files/excel-demo/demo.xlsx").unwrap();
+        let thumbnail = vec![0x89, b'P', b'N', b'G', 0, 1, 2, 255];
+        package
+            .set_thumbnail(thumbnail.clone(), "image/png")
+            .unwrap();
+
+        let xml = package.to_flat_opc().unwrap();
+        assert!(xml.contains(r#"pkg:name="/xl/workbook.xml""#));
+        assert!(xml.contains("<pkg:binaryData>"));
+
+        let flat = OpenXmlPackage::from_flat_opc(&xml).unwrap();
+        assert_eq!(flat.parts().count(), package.parts().count());
+        for part in package.parts() {
+            let flat_part = flat.get_part(part.name().as_str()).unwrap();
+            assert_eq!(flat_part.content_type(), part.content_type());
+        }
+        assert_eq!(flat.relationships(), package.relationships());
+        assert_eq!(
+            flat.thumbnail().unwrap(),
+            Some((thumbnail.as_slice(), "image/png"))
+        );
+
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        flat.write_to(&mut bytes).unwrap();
+        let document = SpreadsheetDocument::from_bytes(bytes.into_inner()).unwrap();
+        assert_eq!(
+            document.get_workbook().worksheet_names(),
+            ["Sheet1", "Sheet2"]
+        );
+
+        assert!(OpenXmlPackage::from_flat_opc("<package/>").is_err());
+    }
+
+    #[test]
+    fn test_round_trip_unknown_content() {
+        let files = [
+            (
+                "[Content_Types].xml",
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><!-- kept --><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Default Extension="bin" ContentType="application/vnd.ms-office.vbaProject"/><Override PartName="/docProps/app.xml" ContentType="application/vnd.openxmlformats-officedocument.extended-properties+xml"/></Types>"#,
+            ),
+            (
+                "_rels/.rels",
+                r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/><Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/extended-properties" Target="docProps/app.xml"/></Relationships>"#,
+            ),
+            (
+                "docProps/app.xml",
+                r#"<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties" xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes"><Application>Microsoft Excel</Application><x:Custom xmlns:x="urn:x">keep</x:Custom><HeadingPairs><vt:vector size="2" baseType="variant"><vt:variant><vt:lpstr>Worksheets</vt:lpstr></vt:variant><vt:variant><vt:i4>1</vt:i4></vt:variant></vt:vector></HeadingPairs><TitlesOfParts><vt:vector size="1" baseType="lpstr"><vt:lpstr>Old</vt:lpstr></vt:vector></TitlesOfParts></Properties>"#,
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="http://schemas.microsoft.com/office/2006/relationships/vbaProject" Target="vbaProject.bin"/></Relationships>"#,
+            ),
+            (
+                "xl/workbook.xml",
+                r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:mc="http://schemas.openxmlformats.org/markup-compatibility/2006" xmlns:x15="http://schemas.microsoft.com/office/spreadsheetml/2010/11/main" mc:Ignorable="x15"><workbookPr codeName="ThisWorkbook"/><mc:AlternateContent><mc:Choice Requires="x15"><x15ac:absPath xmlns:x15ac="http://schemas.microsoft.com/office/spreadsheetml/2010/11/ac" url="C:\"/></mc:Choice></mc:AlternateContent><bookViews><workbookView/></bookViews><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/></sheets><extLst><ext uri="{140A7094-0E35-4892-8432-C4D2E57EDEB5}"/></extLst></workbook>"#,
+            ),
+            (
+                "xl/worksheets/sheet1.xml",
+                r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:mc="http://schemas.openxmlformats.org/markup-compatibility/2006" xmlns:x14ac="http://schemas.microsoft.com/office/spreadsheetml/2009/9/ac" mc:Ignorable="x14ac"><sheetFormatPr defaultRowHeight="15" x14ac:dyDescent="0.25"/><sheetData><row r="1"><c r="A1"><v>1</v></c></row></sheetData><extLst><ext uri="{78C0D931-6437-407d-A8EE-F0AAD7539E65}"/></extLst></worksheet>"#,
+            ),
+            ("xl/vbaProject.bin", "\u{0}\u{1}\u{2}VBA"),
+            (
+                "customXml/item1.xml",
+                r#"<root xmlns="urn:custom"><value>1</value></root>"#,
+            ),
+        ];
+        let mut document = SpreadsheetDocument::from_bytes(zip_package(&files)).unwrap();
+        let output = std::env::temp_dir().join("opc-test-round-trip.xlsm");
+        document.save(&output).unwrap();
+
+        let mut saved = zip::ZipArchive::new(std::fs::File::open(&output).unwrap()).unwrap();
+        for (name, data) in files.iter() {
+            let mut entry = saved.by_name(name).unwrap();
+            let mut saved_data = String::new();
+            std::io::Read::read_to_string(&mut entry, &mut saved_data).unwrap();
+            if *name == "docProps/app.xml" {
+                // Regenerated with the sheet titles, the unknown element stays in place.
+                assert!(
+                    saved_data
+                        .contains(r#"</Application><x:Custom xmlns:x="urn:x">keep</x:Custom>"#),
+                    "{}",
+                    saved_data
+                );
+                assert!(saved_data.contains("<vt:lpstr>Sheet1</vt:lpstr>"));
+            } else {
+                assert_eq!(saved_data, *data, "{}", name);
+            }
+        }
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn test_piece_parts() {
+        let workbook = r#"<workbook><bookViews><workbookView/></bookViews><workbookPr/><sheets><sheet name="pieces" sheetId="1" r:id="rId1"/></sheets></workbook>"#;
+        let (head, tail) = workbook.split_at(40);
+        let (middle, tail) = tail.split_at(40);
+        let files = [
+            (
+                "[Content_Types].xml/[0].piece",
+                r#"<Types><Default Extension="xml" "#,
+            ),
+            (
+                "[Content_Types].xml/[1].last.piece",
+                r#"ContentType="application/xml"/></Types>"#,
+            ),
+            (
+                "_rels/.rels",
+                r#"<Relationships><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#,
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                r#"<Relationships><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/></Relationships>"#,
+            ),
+            ("xl/workbook.xml/[1].piece", middle),
+            ("xl/workbook.xml/[0].piece", head),
+            (
+                "xl/worksheets/sheet1.xml",
+                r#"<worksheet><sheetData/></worksheet>"#,
+            ),
+            ("xl/workbook.xml/[2].LAST.piece", tail),
+        ];
+        let bytes = zip_package(&files);
+
+        let package = OpenXmlPackage::from_bytes(&bytes).unwrap();
+        let names: Vec<String> = package
+            .parts()
+            .map(|part| part.name().to_string())
+            .collect();
+        assert_eq!(names, ["/xl/workbook.xml", "/xl/worksheets/sheet1.xml"]);
+        let part = package.get_part("xl/workbook.xml").unwrap();
+        assert_eq!(part.data().unwrap(), workbook.as_bytes());
+        assert_eq!(part.size(), workbook.len() as u64);
+
+        let lazy = OpenXmlPackage::from_reader_with_options(
+            std::io::Cursor::new(bytes.clone()),
+            OpenOptions::lazy(),
+        )
+        .unwrap();
+        let part = lazy.get_part("xl/workbook.xml").unwrap();
+        assert_eq!(part.data().unwrap(), workbook.as_bytes());
+
+        let document = SpreadsheetDocument::from_bytes(&bytes).unwrap();
+        assert_eq!(document.get_workbook().worksheet_names(), ["pieces"]);
+
+        let mut missing = files.to_vec();
+        missing.retain(|(name, _)| *name != "xl/workbook.xml/[1].piece");
+        match OpenXmlPackage::from_bytes(zip_package(&missing)).unwrap_err() {
+            OoxmlError::InvalidPieces { part, .. } => assert_eq!(part, "/xl/workbook.xml"),
+            e => panic!("unexpected error: {:?}", e),
+        }
+
+        // Pieces are grouped by part name equivalence, non-ASCII case folding included.
+        let mut folded = files.to_vec();
+        folded.extend([
+            ("xl/Ä.xml/[0].piece", "a"),
+            ("xl/ä.xml/[1].last.piece", "b"),
+        ]);
+        let package = OpenXmlPackage::from_bytes(zip_package(&folded)).unwrap();
+        assert_eq!(
+            package.get_part("/xl/ä.xml").unwrap().data().unwrap(),
+            b"ab"
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let package = OpenXmlPackage::open("resources/files/excel-demo/demo.xlsx").unwrap();
+        assert_eq!(package.validate(), vec![]);
+
+        let bytes = zip_package(&[
+            (
+                "[Content_Types].xml",
+                r#"<Types><Default Extension="xml" ContentType="application/xml"/><Override PartName="/docProps/core.xml" ContentType="application/vnd.openxmlformats-package.core-properties+xml"/></Types>"#,
+            ),
+            (
+                "_rels/.rels",
+                r#"<Relationships><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/missing.xml"/><Relationship Id="rId1" Type="urn:a" Target="a.xml"/><Relationship Id="rId2" Type="urn:b" Target="https://example.com/" TargetMode="External"/><Relationship Id="rId3" Type="urn:c" Target="missing.xml"/></Relationships>"#,
+            ),
+            ("a.xml", "<a/>"),
+            ("A.XML", "<a/>"),
+            ("media/image.bin", ""),
+            ("bad./name.xml", "<a/>"),
+        ]);
+        let package = OpenXmlPackage::from_bytes(bytes).unwrap();
+        let diagnostics = package.validate();
+        let kinds: Vec<DiagnosticKind> = diagnostics.iter().map(|d| d.kind()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DiagnosticKind::DuplicatePartName,
+                DiagnosticKind::MissingContentType,
+                DiagnosticKind::IllegalPartName,
+                DiagnosticKind::OverrideWithoutPart,
+                DiagnosticKind::DuplicateRelationshipId,
+                DiagnosticKind::DanglingRelationship,
+                DiagnosticKind::DanglingRelationship,
+                DiagnosticKind::MissingMainDocument,
+            ],
+            "{:#?}",
+            diagnostics
+        );
+        assert_eq!(diagnostics[1].part(), Some("/media/image.bin"));
+        // The first rId1 is kept, its officeDocument target is missing.
+        assert!(diagnostics[5].message().contains("/xl/missing.xml"));
+        assert!(diagnostics[6].message().contains("/missing.xml"));
+    }
+
+    /// Copy the zip at `path` with the text entries passed through `edit` and `extra`
+    /// entries appended.
+    fn rewrite_zip(
+        path: &str,
+        extra: &[(&str, &str)],
+        edit: impl Fn(&str, String) -> String,
+    ) -> Vec<u8> {
+        use std::io::{Read, Write};
+        let mut zip = zip::ZipArchive::new(std::fs::File::open(path).unwrap()).unwrap();
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i).unwrap();
+            let name = file.name().to_string();
+            let mut data = String::new();
+            file.read_to_string(&mut data).unwrap();
+            let data = edit(&name, data);
+            writer.start_file(name, options).unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+        }
+        for (name, data) in extra.iter() {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(data.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let path = "resources/files/signed/signed.xlsx";
+        let package = OpenXmlPackage::open(path).unwrap();
+        let signatures = package.signatures().unwrap();
+        assert_eq!(signatures.len(), 1);
+
+        let signature = &signatures[0];
+        assert_eq!(signature.part_name(), "/_xmlsignatures/sig1.xml");
+        assert_eq!(
+            signature.signature_time().as_deref(),
+            Some("2021-03-01T10:00:00Z")
+        );
+        let signer = signature.signer().unwrap();
+        assert_eq!(signer.subject(), "O=opc,CN=Test Signer");
+        assert_eq!(signer.serial_number(), "1234");
+        assert!(signature
+            .signed_parts()
+            .contains(&"/xl/_rels/workbook.xml.rels".to_string()));
+
+        let verification = signature.verify(&package).unwrap();
+        assert!(verification.is_valid(), "{:#?}", verification);
+        assert_eq!(verification.references().len(), 9);
+
+        // Tamper with a signed part.
+        let bytes = rewrite_zip(path, &[], |name, data| match name {
+            "xl/sharedStrings.xml" => data.replace("<t>", "<t>x"),
+            _ => data,
+        });
+        let package = OpenXmlPackage::from_bytes(bytes).unwrap();
+        let verification = package.signatures().unwrap()[0].verify(&package).unwrap();
+        assert!(verification.signature_value_valid());
+        assert!(!verification.is_valid());
+        let tampered: Vec<&str> = verification
+            .references()
+            .iter()
+            .filter(|r| *r.status() == ReferenceStatus::DigestMismatch)
+            .map(|r| r.uri())
+            .collect();
+        assert_eq!(tampered.len(), 1);
+        assert!(tampered[0].starts_with("/xl/sharedStrings.xml?"));
+    }
+
+    #[test]
+    fn test_verify_unsigned_manifest() {
+        use base64::Engine;
+        use sha2::Digest;
+
+        // Append an Object that SignedInfo does not reference, whose manifest signs an
+        // injected part with a correct digest.
+        let evil = "<evil/>";
+        let digest =
+            base64::engine::general_purpose::STANDARD.encode(sha2::Sha256::digest(evil.as_bytes()));
+        let object = format!(
+            r#"<Object Id="x"><Manifest><Reference URI="/xl/evil.xml?ContentType=application/xml"><DigestMethod Algorithm="http://www.w3.org/2001/04/xmlenc#sha256"/><DigestValue>{}</DigestValue></Reference></Manifest></Object></Signature>"#,
+            digest
+        );
+        let bytes = rewrite_zip(
+            "resources/files/signed/signed.xlsx",
+            &[("xl/evil.xml", evil)],
+            |name, data| match name {
+                "_xmlsignatures/sig1.xml" => data.replace("</Signature>", &object),
+                _ => data,
+            },
+        );
+        let package = OpenXmlPackage::from_bytes(bytes).unwrap();
+        let signature = &package.signatures().unwrap()[0];
+        assert!(!signature
+            .signed_parts()
+            .contains(&"/xl/evil.xml".to_string()));
+        assert_eq!(signature.signed_parts().len(), 8);
+
+        let verification = signature.verify(&package).unwrap();
+        assert!(verification.signature_value_valid());
+        assert!(!verification.is_valid());
+        let invalid: Vec<_> = verification
+            .references()
+            .iter()
+            .filter(|r| *r.status() != ReferenceStatus::Valid)
+            .collect();
+        assert_eq!(invalid.len(), 1);
+        assert!(invalid[0].uri().starts_with("/xl/evil.xml?"));
+        assert!(matches!(
+            invalid[0].status(),
+            ReferenceStatus::Unsupported(_)
+        ));
+    }
+
+    /// Interoperability check against a signature produced by libxmlsec1, see
+    /// `resources/files/signed/README.md`.
+    ///
+    /// `signed.xlsx` is signed by this crate, so it cannot catch a canonicalization or
+    /// digest mistake made the same way when signing and verifying.
+    #[test]
+    fn test_verify_xmlsec_signature() {
+        let path = "resources/files/signed/xmlsec-signed.xlsx";
+        let package = OpenXmlPackage::open(path).unwrap();
+        let signatures = package.signatures().unwrap();
+        assert_eq!(signatures.len(), 1);
+
+        let signature = &signatures[0];
+        assert_eq!(
+            signature.signature_time().as_deref(),
+            Some("2021-03-01T10:00:00Z")
+        );
+        assert_eq!(signature.signed_parts().len(), 7);
+        let verification = signature.verify(&package).unwrap();
+        assert!(verification.is_valid(), "{:#?}", verification);
+        assert_eq!(verification.references().len(), 9);
+
+        // Both the inclusive canonicalization of SignedInfo and the exclusive one of the
+        // Office object are checked against the libxmlsec1 digests.
+        let bytes = rewrite_zip(path, &[], |name, data| match name {
+            "_xmlsignatures/sig1.xml" => data.replace("<Monitors>1<", "<Monitors>2<"),
+            _ => data,
+        });
+        let package = OpenXmlPackage::from_bytes(bytes).unwrap();
+        let verification = package.signatures().unwrap()[0].verify(&package).unwrap();
+        assert!(verification.signature_value_valid());
+        assert_eq!(
+            verification.references()[1].status(),
+            &ReferenceStatus::DigestMismatch
+        );
+    }
+
+    #[test]
+    fn test_sign_package() {
+        let certificate =
+            std::fs::read_to_string("resources/files/signed/signer-cert.pem").unwrap();
+        let private_key = std::fs::read_to_string("resources/files/signed/signer-key.pem").unwrap();
+        let key = SigningKey::from_pem(&certificate, &private_key).unwrap();
+
+        let mut package = OpenXmlPackage::open("resources/files/e1.xlsx").unwrap();
+        let part_name = package.sign(&key, &SignOptions::default()).unwrap();
+        assert_eq!(part_name.as_str(), "/_xmlsignatures/sig1.xml");
+
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        package.write_to(&mut bytes).unwrap();
+        let mut package = OpenXmlPackage::from_bytes(bytes.into_inner()).unwrap();
+        assert_eq!(package.validate(), vec![]);
+
+        let pkcs12 = std::fs::read("resources/files/signed/signer.p12").unwrap();
+        let key = SigningKey::from_pkcs12(&pkcs12, "opc").unwrap();
+        assert_eq!(key.certificate().subject(), "O=opc,CN=Test Signer");
+        let options = SignOptions {
+            parts: vec!["/xl/workbook.xml".into()],
+            ..Default::default()
+        };
+        let part_name = package.sign(&key, &options).unwrap();
+        assert_eq!(part_name.as_str(), "/_xmlsignatures/sig2.xml");
+
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        package.write_to(&mut bytes).unwrap();
+        let package = OpenXmlPackage::from_bytes(bytes.into_inner()).unwrap();
+        let signatures = package.signatures().unwrap();
+        assert_eq!(signatures.len(), 2);
+        for signature in &signatures {
+            let verification = signature.verify(&package).unwrap();
+            assert!(verification.is_valid(), "{:#?}", verification);
+        }
+        assert_eq!(signatures[0].signed_parts().len(), 9);
+        assert_eq!(
+            signatures[1].signed_parts(),
+            vec![
+                "/_rels/.rels",
+                "/xl/_rels/workbook.xml.rels",
+                "/xl/workbook.xml"
+            ]
+        );
+
+        assert!(SigningKey::from_pkcs12(&pkcs12, "wrong").is_err());
+    }
+
+    #[test]
+    fn test_open_with_password() {
+        for path in [
+            "resources/files/encrypted/agile.xlsx",
+            "resources/files/encrypted/standard.xlsx",
+        ] {
+            assert!(matches!(
+                OpenXmlPackage::open(path).unwrap_err(),
+                OoxmlError::PasswordRequired
+            ));
+            assert!(matches!(
+                OpenXmlPackage::open_with_password(path, "password1").unwrap_err(),
+                OoxmlError::InvalidPassword
+            ));
+
+            let package = OpenXmlPackage::open_with_password(path, "Password1").unwrap();
+            let expected = OpenXmlPackage::open("resources/files/e1.xlsx").unwrap();
+            assert_eq!(
+                package
+                    .get_part("/xl/workbook.xml")
+                    .unwrap()
+                    .data()
+                    .unwrap(),
+                expected
+                    .get_part("/xl/workbook.xml")
+                    .unwrap()
+                    .data()
+                    .unwrap()
+            );
+        }
+
+        // Unencrypted packages open as usual.
+        OpenXmlPackage::open_with_password("resources/files/e1.xlsx", "Password1").unwrap();
+    }
+
+    #[test]
+    fn test_encrypted_package_limits() {
+        use std::io::{Cursor, Write};
+
+        let path = "resources/files/encrypted/agile.xlsx";
+        let encrypted = std::fs::read(path).unwrap();
+        let options = OpenOptions {
+            max_total_size: Some(1024),
+            ..Default::default()
+        };
+        assert!(matches!(
+            OpenXmlPackage::from_reader_with_password_and_options(
+                Cursor::new(encrypted.clone()),
+                "Password1",
+                options
+            )
+            .unwrap_err(),
+            OoxmlError::PackageTooLarge { limit: 1024 }
+        ));
+
+        // A forged package size is rejected before anything is allocated for it.
+        let mut compound_file = cfb::CompoundFile::open(Cursor::new(encrypted)).unwrap();
+        compound_file
+            .open_stream("/EncryptedPackage")
+            .unwrap()
+            .write_all(&u64::MAX.to_le_bytes())
+            .unwrap();
+        let forged = compound_file.into_inner().into_inner();
+        match OpenXmlPackage::from_reader_with_password(Cursor::new(forged), "Password1") {
+            Err(OoxmlError::EncryptionError(reason)) => assert!(reason.contains("size")),
+            result => panic!("unexpected result {:?}", result.map(|_| ())),
+        }
+
+        // Key parameters that would allocate or hash without bound are rejected.
+        let with_encryption_info = |path: &str, edit: &dyn Fn(Vec<u8>) -> Vec<u8>| {
+            use std::io::Read;
+            let encrypted = std::fs::read(path).unwrap();
+            let mut compound_file = cfb::CompoundFile::open(Cursor::new(encrypted)).unwrap();
+            let mut info = Vec::new();
+            compound_file
+                .open_stream("/EncryptionInfo")
+                .unwrap()
+                .read_to_end(&mut info)
+                .unwrap();
+            compound_file
+                .create_stream("/EncryptionInfo")
+                .unwrap()
+                .write_all(&edit(info))
+                .unwrap();
+            let forged = compound_file.into_inner().into_inner();
+            OpenXmlPackage::from_reader_with_password(Cursor::new(forged), "Password1")
+        };
+        for (from, to, reason) in [
+            (
+                r#"blockSize="16""#,
+                r#"blockSize="1000000000000""#,
+                "block size",
+            ),
+            (r#"keyBits="256""#, r#"keyBits="8000000000000""#, "key size"),
+            (
+                r#"spinCount="100000""#,
+                r#"spinCount="4294967295""#,
+                "spin count",
+            ),
+        ] {
+            let result = with_encryption_info(path, &|info| {
+                let (header, xml) = info.split_at(8);
+                let xml = String::from_utf8(xml.to_vec()).unwrap();
+                assert!(xml.contains(from), "{}", xml);
+                [header, xml.replace(from, to).as_bytes()].concat()
+            });
+            match result {
+                Err(OoxmlError::EncryptionError(message)) => assert!(message.contains(reason)),
+                result => panic!("unexpected result {:?}", result.map(|_| ())),
+            }
+        }
+        // A verifier hash size beyond the decrypted verifier hash would accept any password.
+        let result =
+            with_encryption_info("resources/files/encrypted/standard.xlsx", &|mut info| {
+                let read_u32 = |info: &[u8], at: usize| {
+                    u32::from_le_bytes([info[at], info[at + 1], info[at + 2], info[at + 3]])
+                        as usize
+                };
+                let verifier = 12 + read_u32(&info, 8);
+                let at = verifier + 20 + read_u32(&info, verifier);
+                assert_eq!(read_u32(&info, at), 20);
+                info[at..at + 4].copy_from_slice(&64u32.to_le_bytes());
+                info
+            });
+        match result {
+            Err(OoxmlError::EncryptionError(message)) => {
+                assert!(message.contains("verifier hash size"))
+            }
+            result => panic!("unexpected result {:?}", result.map(|_| ())),
+        }
+
+        // A compound file without an encrypted package, e.g. a legacy .xls workbook.
+        let mut compound_file = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+        compound_file
+            .create_stream("/Workbook")
+            .unwrap()
+            .write_all(b"BIFF")
+            .unwrap();
+        let xls = compound_file.into_inner().into_inner();
+        for result in [
+            OpenXmlPackage::from_bytes(&xls),
+            OpenXmlPackage::from_reader_with_password(Cursor::new(&xls), "Password1"),
+        ] {
+            assert!(
+                matches!(result, Err(OoxmlError::ZipError(_))),
+                "{:?}",
+                result.map(|_| ())
+            );
+        }
+    }
+
+    #[test]
+    fn test_save_with_password() {
+        let package = OpenXmlPackage::open("resources/files/e1.xlsx").unwrap();
+        let mut encrypted = Vec::new();
+        package
+            .write_to_with_password(&mut encrypted, "Password1")
+            .unwrap();
+
+        assert!(matches!(
+            OpenXmlPackage::from_bytes(encrypted.clone()).unwrap_err(),
+            OoxmlError::PasswordRequired
+        ));
+        assert!(matches!(
+            OpenXmlPackage::from_reader_with_password(std::io::Cursor::new(&encrypted), "x")
+                .unwrap_err(),
+            OoxmlError::InvalidPassword
+        ));
+
+        let decrypted = OpenXmlPackage::from_reader_with_password(
+            std::io::Cursor::new(&encrypted),
+            "Password1",
+        )
+        .unwrap();
+        for name in [
+            "/xl/workbook.xml",
+            "/xl/styles.xml",
+            "/xl/worksheets/sheet1.xml",
+        ] {
+            assert_eq!(
+                decrypted.get_part(name).unwrap().data().unwrap(),
+                package.get_part(name).unwrap().data().unwrap(),
+            );
+        }
+    }
+
+    #[test]
+    fn test_core_properties() {
+        let mut package = OpenXmlPackage::open("resources/files/e1.xlsx").unwrap();
+        let created = Utc.with_ymd_and_hms(2021, 7, 7, 2, 32, 26).unwrap();
+        assert_eq!(package.properties().created, Some(created));
+        assert_eq!(
+            package.properties().creator.as_deref(),
+            Some("Microsoft Office User")
+        );
+
+        let mut properties = package.properties().clone();
+        let modified = Utc.with_ymd_and_hms(2022, 1, 2, 3, 4, 5).unwrap();
+        properties.set_title("Quarterly <report>");
+        properties.set_modified(modified);
+        package.set_properties(properties).unwrap();
+
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        package.write_to(&mut bytes).unwrap();
+        let saved = OpenXmlPackage::from_bytes(bytes.into_inner()).unwrap();
+        let properties = saved.properties();
+        assert_eq!(properties.title.as_deref(), Some("Quarterly <report>"));
+        assert_eq!(properties.created, Some(created));
+        assert_eq!(properties.modified, Some(modified));
+        let core = saved
+            .get_part("/docProps/core.xml")
+            .unwrap()
+            .data()
+            .unwrap();
+        let core = std::str::from_utf8(core).unwrap();
+        assert!(core.contains(
+            r#"<dcterms:modified xsi:type="dcterms:W3CDTF">2022-01-02T03:04:05Z</dcterms:modified>"#
+        ));
+
+        // Packages without core properties get a new part and relationship.
+        let mut package = OpenXmlPackage::from_bytes(zip_package(&[
+            (
+                "[Content_Types].xml",
+                r#"<Types><Default Extension="xml" ContentType="application/xml"/></Types>"#,
+            ),
+            ("_rels/.rels", "<Relationships/>"),
+        ]))
+        .unwrap();
+        let mut properties = package.properties().clone();
+        properties.set_creator("opc");
+        package.set_properties(properties).unwrap();
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        package.write_to(&mut bytes).unwrap();
+        let saved = OpenXmlPackage::from_bytes(bytes.into_inner()).unwrap();
+        assert_eq!(saved.properties().creator.as_deref(), Some("opc"));
+        assert!(saved
+            .validate()
+            .iter()
+            .all(|diagnostic| diagnostic.kind() == DiagnosticKind::MissingMainDocument));
+    }
+
+    #[test]
+    fn test_custom_properties() {
+        let mut package = OpenXmlPackage::open("resources/files/excel-demo/demo.xlsx").unwrap();
+        let properties = package.custom_properties();
+        assert_eq!(
+            properties["KSOProductBuildVer"].as_str(),
+            Some("2052-11.1.0.9662")
+        );
+
+        package.set_custom_property("ProjectCode", "PX-42").unwrap();
+        package.set_custom_property("Classification", 3).unwrap();
+        package.set_custom_property("Reviewed", true).unwrap();
+        assert_eq!(
+            package.remove_custom_property("Reviewed").unwrap(),
+            Some(Variant::VtBool(true))
+        );
+
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        package.write_to(&mut bytes).unwrap();
+        let saved = OpenXmlPackage::from_bytes(bytes.into_inner()).unwrap();
+        let properties = saved.custom_properties();
+        assert_eq!(
+            properties.keys().collect::<Vec<_>>(),
+            ["KSOProductBuildVer", "ProjectCode", "Classification"]
+        );
+        assert_eq!(properties["ProjectCode"].as_str(), Some("PX-42"));
+        assert_eq!(properties["Classification"].as_i64(), Some(3));
+        let custom = saved
+            .get_part("/docProps/custom.xml")
+            .unwrap()
+            .data()
+            .unwrap();
+        let custom = std::str::from_utf8(custom).unwrap();
+        assert!(custom.contains(
+            r#"<property fmtid="{D5CDD505-2E9C-101B-9397-08002B2CF9AE}" pid="4" name="Classification"><vt:i4>3</vt:i4></property>"#
+        ));
+
+        // Packages without custom properties get a new part, relationship and override.
+        let mut package = OpenXmlPackage::open("resources/files/e1.xlsx").unwrap();
+        assert!(package.custom_properties().is_empty());
+        package.set_custom_property("ProjectCode", "PX-42").unwrap();
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        package.write_to(&mut bytes).unwrap();
+        let saved = OpenXmlPackage::from_bytes(bytes.into_inner()).unwrap();
+        assert_eq!(
+            saved.custom_properties()["ProjectCode"].as_str(),
+            Some("PX-42")
+        );
+        assert!(saved.validate().is_empty());
+    }
+
+    #[test]
+    fn test_app_properties() {
+        let mut package = OpenXmlPackage::open("resources/files/e1.xlsx").unwrap();
+        let properties = package.app_properties();
+        assert_eq!(
+            properties.application.as_deref(),
+            Some("Microsoft Macintosh Excel")
+        );
+        assert_eq!(
+            properties.titles_of_parts(),
+            [("工作表".to_string(), vec!["Sheet1".to_string()])]
+        );
+
+        let mut properties = properties.clone();
+        properties.set_company("Contoso");
+        properties.set_manager("Alice");
+        properties.set_titles_of_parts(vec![("Worksheets", vec!["Old"])]);
+        package.set_app_properties(properties).unwrap();
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        package.write_to(&mut bytes).unwrap();
+        let saved = OpenXmlPackage::from_bytes(bytes.get_ref()).unwrap();
+        let properties = saved.app_properties();
+        assert_eq!(properties.company.as_deref(), Some("Contoso"));
+        assert_eq!(properties.manager.as_deref(), Some("Alice"));
+        assert_eq!(properties.app_version.as_deref(), Some("16.0300"));
+        assert_eq!(
+            properties.titles_of_parts(),
+            [("Worksheets".to_string(), vec!["Old".to_string()])]
+        );
+
+        // Saving a spreadsheet regenerates the titles from the workbook sheets.
+        let mut document = SpreadsheetDocument::from_bytes(bytes.into_inner()).unwrap();
+        let output = std::env::temp_dir().join("opc-test-app-properties.xlsx");
+        document.save(&output).unwrap();
+        let saved = OpenXmlPackage::open(&output).unwrap();
+        assert_eq!(
+            saved.app_properties().titles_of_parts(),
+            [("Worksheets".to_string(), vec!["Sheet1".to_string()])]
+        );
+        assert_eq!(saved.app_properties().company.as_deref(), Some("Contoso"));
+        std::fs::remove_file(output).unwrap();
+
+        // Worksheets and chartsheets go to their own headings, localized names are kept
+        // and headings of other parts are left alone.
+        const RELATIONSHIPS: &str =
+            "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+        let app = |titles: &str| {
+            format!(
+                r#"<Properties xmlns="http://schemas.openxmlformats.org/officeDocument/2006/extended-properties" xmlns:vt="http://schemas.openxmlformats.org/officeDocument/2006/docPropsVTypes">{}</Properties>"#,
+                titles
+            )
+        };
+        let root_rels = format!(
+            r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="{0}/officeDocument" Target="xl/workbook.xml"/><Relationship Id="rId2" Type="{0}/extended-properties" Target="docProps/app.xml"/></Relationships>"#,
+            RELATIONSHIPS
+        );
+        let workbook_rels = format!(
+            r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="{0}/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="{0}/chartsheet" Target="chartsheets/sheet1.xml"/><Relationship Id="rId3" Type="{0}/worksheet" Target="worksheets/sheet2.xml"/></Relationships>"#,
+            RELATIONSHIPS
+        );
+        let app_xml = app(
+            r#"<HeadingPairs><vt:vector size="4" baseType="variant"><vt:variant><vt:lpstr>工作表</vt:lpstr></vt:variant><vt:variant><vt:i4>1</vt:i4></vt:variant><vt:variant><vt:lpstr>Named Ranges</vt:lpstr></vt:variant><vt:variant><vt:i4>1</vt:i4></vt:variant></vt:vector></HeadingPairs><TitlesOfParts><vt:vector size="2" baseType="lpstr"><vt:lpstr>Data</vt:lpstr><vt:lpstr>Data!Print_Area</vt:lpstr></vt:vector></TitlesOfParts>"#,
+        );
+        let sheet = r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData/></worksheet>"#;
+        let files = [
+            (
+                "[Content_Types].xml",
+                r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/></Types>"#,
+            ),
+            ("_rels/.rels", root_rels.as_str()),
+            ("docProps/app.xml", app_xml.as_str()),
+            ("xl/_rels/workbook.xml.rels", workbook_rels.as_str()),
+            (
+                "xl/workbook.xml",
+                r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><workbookPr/><bookViews><workbookView/></bookViews><sheets><sheet name="Data" sheetId="1" r:id="rId1"/><sheet name="Chart1" sheetId="2" r:id="rId2"/><sheet name="Summary" sheetId="3" r:id="rId3"/></sheets></workbook>"#,
+            ),
+            ("xl/worksheets/sheet1.xml", sheet),
+            ("xl/worksheets/sheet2.xml", sheet),
+            ("xl/chartsheets/sheet1.xml", sheet),
+        ];
+        let mut document = SpreadsheetDocument::from_bytes(zip_package(&files)).unwrap();
+        document.update_app_properties().unwrap();
+        assert_eq!(
+            document.package().app_properties().titles_of_parts(),
+            [
+                (
+                    "工作表".to_string(),
+                    vec!["Data".to_string(), "Summary".to_string()]
+                ),
+                ("Charts".to_string(), vec!["Chart1".to_string()]),
+                (
+                    "Named Ranges".to_string(),
+                    vec!["Data!Print_Area".to_string()]
+                ),
+            ]
+        );
+
+        // Saving a workbook without extended properties does not create them.
+        let files: Vec<_> = files
+            .iter()
+            .filter(|(name, _)| *name != "docProps/app.xml")
+            .copied()
+            .collect();
+        let root_rels = root_rels.replace(
+            &format!(
+                r#"<Relationship Id="rId2" Type="{}/extended-properties" Target="docProps/app.xml"/>"#,
+                RELATIONSHIPS
+            ),
+            "",
+        );
+        let files: Vec<_> = files
+            .into_iter()
+            .map(|(name, data)| match name {
+                "_rels/.rels" => (name, root_rels.as_str()),
+                _ => (name, data),
+            })
+            .collect();
+        let mut document = SpreadsheetDocument::from_bytes(zip_package(&files)).unwrap();
+        document.update_app_properties().unwrap();
+        let package = document.package();
+        assert!(package.get_part("/docProps/app.xml").is_none());
+        assert_eq!(package.content_types().overrides().count(), 0);
+    }
+
+    #[test]
+    fn test_thumbnail() {
+        let mut package = OpenXmlPackage::open("resources/files/e1.xlsx").unwrap();
+        assert!(package.thumbnail().unwrap().is_none());
+        assert!(!package.remove_thumbnail());
+
+        let image = b"\x89PNG\r\n\x1a\nthumbnail".to_vec();
+        package.set_thumbnail(image.clone(), "image/png").unwrap();
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        package.write_to(&mut bytes).unwrap();
+        let mut saved = OpenXmlPackage::from_bytes(bytes.into_inner()).unwrap();
+        assert_eq!(
+            saved.thumbnail().unwrap(),
+            Some((image.as_slice(), "image/png"))
+        );
+        assert!(saved.get_part("/docProps/thumbnail.png").is_some());
+        assert!(saved.validate().is_empty());
+
+        // Replacing the thumbnail renames the part after the new content type.
+        saved.set_thumbnail(b"jpeg".to_vec(), "image/jpeg").unwrap();
+        assert!(saved.get_part("/docProps/thumbnail.png").is_none());
+        assert_eq!(
+            saved.thumbnail().unwrap(),
+            Some((&b"jpeg"[..], "image/jpeg"))
+        );
+
+        assert!(saved.remove_thumbnail());
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        saved.write_to(&mut bytes).unwrap();
+        let saved = OpenXmlPackage::from_bytes(bytes.into_inner()).unwrap();
+        assert!(saved.thumbnail().unwrap().is_none());
+        assert!(saved.get_part("/docProps/thumbnail.jpeg").is_none());
+        assert!(saved.validate().is_empty());
+    }
+
+    #[test]
+    fn test_open_limits() {
+        let zeros = "0".repeat(1 << 20);
+        let nested = format!("{}{}", "<a>".repeat(20), "</a>".repeat(20));
+        let bytes = zip_package(&[
+            (
+                "[Content_Types].xml",
+                r#"<Types><Default Extension="xml" ContentType="application/xml"/></Types>"#,
+            ),
+            ("_rels/.rels", "<Relationships/>"),
+            ("zeros.txt", &zeros),
+            ("nested.xml", &nested),
+        ]);
+        let open = |options: OpenOptions| {
+            OpenXmlPackage::from_reader_with_options(std::io::Cursor::new(bytes.clone()), options)
+        };
+
+        assert!(open(OpenOptions::default()).is_ok());
+        assert!(matches!(
+            open(OpenOptions {
+                max_part_size: Some(1000),
+                ..Default::default()
+            }),
+            Err(OoxmlError::PartTooLarge { part, limit: 1000 }) if part == "/zeros.txt"
+        ));
+        assert!(matches!(
+            open(OpenOptions {
+                max_total_size: Some(1 << 20),
+                ..Default::default()
+            }),
+            Err(OoxmlError::PackageTooLarge { limit }) if limit == 1 << 20
+        ));
+        assert!(matches!(
+            open(OpenOptions {
+                max_part_count: Some(3),
+                ..Default::default()
+            }),
+            Err(OoxmlError::TooManyParts { count: 4, limit: 3 })
+        ));
+        assert!(matches!(
+            open(OpenOptions {
+                max_compression_ratio: Some(100),
+                ..Default::default()
+            }),
+            Err(OoxmlError::CompressionRatioExceeded { part, limit: 100 }) if part == "/zeros.txt"
+        ));
+        assert!(matches!(
+            open(OpenOptions {
+                max_xml_depth: Some(10),
+                ..Default::default()
+            }),
+            Err(OoxmlError::XmlTooDeep { part, limit: 10 }) if part == "/nested.xml"
+        ));
+
+        // Lazy parts are checked when they are first read.
+        let package = open(OpenOptions {
+            max_part_size: Some(1000),
+            ..OpenOptions::lazy()
+        })
+        .unwrap();
+        assert!(matches!(
+            package.get_part("/zeros.txt").unwrap().data(),
+            Err(OoxmlError::PartTooLarge { .. })
+        ));
+
+        let path = "resources/files/excel-demo/demo.xlsx";
+        OpenXmlPackage::open_with_options(path, OpenOptions::untrusted()).unwrap();
+    }
+
+    #[test]
+    fn test_list_parts() {
+        let path = "resources/files/excel-demo/demo.xlsx";
+        let package = OpenXmlPackage::open(path).unwrap();
+        let names: Vec<String> = package.parts().map(|p| p.name().to_string()).collect();
+        assert!(names.contains(&"/xl/workbook.xml".to_string()));
+        assert!(!names.iter().any(|name| name.ends_with(".rels")));
+        for part in package.parts() {
+            assert_eq!(part.size(), part.data().unwrap().len() as u64);
+            assert!(part.compressed_size().is_some());
+            assert!(part.content_type().is_some(), "{}", part.name());
+        }
+
+        let lazy = OpenXmlPackage::open_with_options(path, OpenOptions::lazy()).unwrap();
+        let image = lazy.get_part("/xl/media/image1.png").unwrap();
+        assert!(!image.is_loaded());
+        assert_eq!(
+            image.size(),
+            package.get_part("/xl/media/image1.png").unwrap().size()
+        );
+
+        let property_parts: Vec<String> = package
+            .property_parts()
+            .map(|p| p.name().to_string())
+            .collect();
+        assert_eq!(
+            property_parts,
+            [
+                "/docProps/core.xml",
+                "/docProps/app.xml",
+                "/docProps/custom.xml"
+            ]
+        );
+        assert_eq!(package.relationships().iter().count(), 4);
+        assert!(package
+            .part_relationships()
+            .any(|(source, _)| source.as_str() == "/xl/workbook.xml"));
+    }
+
     #[test]
     fn test_document_open() {
         let path = "resources/files/excel-demo/demo.xlsx";